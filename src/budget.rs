@@ -0,0 +1,114 @@
+use crate::tools::Usage;
+
+/// Cost and token limits for a run, checked before each iteration
+pub struct Budget {
+    max_cost: Option<f64>,
+    max_tokens: Option<u64>,
+    spent: Usage,
+    /// Iterations that reported usage
+    iterations: u32,
+}
+
+impl Budget {
+    pub fn new(max_cost: Option<f64>, max_tokens: Option<u64>) -> Self {
+        Self {
+            max_cost,
+            max_tokens,
+            spent: Usage::default(),
+            iterations: 0,
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.max_cost.is_some() || self.max_tokens.is_some()
+    }
+
+    pub fn record(&mut self, usage: &Usage) {
+        self.spent.add(usage);
+        self.iterations += 1;
+    }
+
+    pub fn spent(&self) -> &Usage {
+        &self.spent
+    }
+
+    /// Why the next iteration should not start: another iteration costing
+    /// the average so far would go over a limit
+    pub fn exceeded(&self) -> Option<String> {
+        if self.iterations == 0 {
+            return None;
+        }
+
+        if let (Some(max), Some(spent)) = (self.max_cost, self.spent.cost_usd) {
+            let next = spent / self.iterations as f64;
+            if spent + next > max {
+                return Some(format!(
+                    "cost budget of ${:.2} would be exceeded (spent ${:.2}, about ${:.2} per iteration)",
+                    max, spent, next
+                ));
+            }
+        }
+
+        if let Some(max) = self.max_tokens {
+            let spent = self.spent.total_tokens();
+            let next = spent / self.iterations as u64;
+            if spent + next > max {
+                return Some(format!(
+                    "token budget of {} would be exceeded (spent {}, about {} per iteration)",
+                    max, spent, next
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(tokens: u64, cost: Option<f64>) -> Usage {
+        Usage {
+            input_tokens: tokens,
+            cost_usd: cost,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_unlimited_budget_never_exceeded() {
+        let mut budget = Budget::new(None, None);
+        budget.record(&usage(1_000_000, Some(100.0)));
+        assert!(!budget.is_limited());
+        assert!(budget.exceeded().is_none());
+    }
+
+    #[test]
+    fn test_cost_budget_stops_before_overrun() {
+        let mut budget = Budget::new(Some(1.0), None);
+        budget.record(&usage(10, Some(0.3)));
+        assert!(budget.exceeded().is_none());
+        budget.record(&usage(10, Some(0.3)));
+        // 0.6 spent, the next ~0.3 would reach 0.9
+        assert!(budget.exceeded().is_none());
+        budget.record(&usage(10, Some(0.3)));
+        assert!(budget.exceeded().unwrap().contains("$1.00"));
+    }
+
+    #[test]
+    fn test_token_budget() {
+        let mut budget = Budget::new(None, Some(250));
+        budget.record(&usage(100, None));
+        assert!(budget.exceeded().is_none());
+        budget.record(&usage(100, None));
+        assert!(budget.exceeded().unwrap().contains("token budget of 250"));
+    }
+
+    #[test]
+    fn test_cost_budget_ignored_without_cost() {
+        let mut budget = Budget::new(Some(0.01), None);
+        budget.record(&usage(1_000_000, None));
+        assert!(budget.exceeded().is_none());
+    }
+}
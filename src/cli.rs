@@ -1,52 +1,238 @@
-use clap::{Parser, ValueEnum};
+use crate::config::parse_duration;
+use crate::stall::StallAction;
+use clap::{Args, Parser, Subcommand};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(
     name = "ralph",
     version,
     about = "Run AI coding tools iteratively on tasks",
-    long_about = None
+    long_about = None,
+    args_conflicts_with_subcommands = true
 )]
 pub struct Cli {
-    /// Number of iterations to run
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Without a subcommand, `ralph -i 5 -t foo` is the same as `ralph run -i 5 -t foo`
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Args, Default)]
+pub struct RunArgs {
+    /// Number of iterations to run (defaults to `iterations` from config)
     #[arg(short = 'i', long)]
-    pub iterations: u32,
+    pub iterations: Option<u32>,
 
     /// Directory path for task (use --task for task names in .ai/tasks/)
     #[arg(short = 'd', long)]
     pub dir: Option<String>,
 
-    /// PRD file path or task name (resolves to .ai/tasks/{name}/PRD.md)
+    /// PRD file path or task name (resolves to .ai/tasks/{name}/PRD.md); repeat to queue several
+    /// tasks, ordered by `depends_on` and `priority` in their PRD front matter
     #[arg(short = 't', long)]
-    pub task: Option<String>,
+    pub task: Vec<String>,
+
+    /// Queue every task under .ai/tasks that is not complete yet
+    #[arg(long, conflicts_with_all = ["task", "dir"])]
+    pub all: bool,
 
-    /// Tool to use for execution
-    #[arg(short = 'T', long, value_enum)]
-    pub tool: ToolChoice,
+    /// Run up to N queued tasks at once, each in its own git worktree on a ralph/<task> branch
+    #[arg(short = 'j', long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub parallel: Option<u32>,
 
-    /// Model name (optional, accepts aliases)
+    /// Tool to use: opencode (oc), claude (cc), codex, gemini (gm), aider, or a command tool from config
+    /// (defaults to `tool` from config)
+    #[arg(short = 'T', long)]
+    pub tool: Option<ToolChoice>,
+
+    /// Model name (optional, accepts aliases); a comma-separated list such as
+    /// haiku,sonnet,opus is an escalation chain, cheapest first
     #[arg(short = 'm', long)]
     pub model: Option<String>,
 
-    /// OpenCode variant (only valid with --tool opencode)
+    /// OpenCode variant (only valid with --tool opencode or command tools using {variant})
     #[arg(short = 'v', long)]
     pub variant: Option<String>,
+
+    /// Skip the confirmation prompt (required when stdin is not a terminal)
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
+    /// Reset the working tree to the pre-iteration checkpoint when an iteration fails
+    #[arg(long)]
+    pub rollback: bool,
+
+    /// Failed iterations in a row before moving to the next model of a --model chain (default 2)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub escalate_after: Option<u32>,
+
+    /// Pick the next unchecked PRD item (by P0/P1 tags, then order) and tell the agent to do exactly that
+    #[arg(long)]
+    pub select_item: bool,
+
+    /// Command that must pass before completion is accepted (repeatable, replaces config)
+    #[arg(long = "verify", value_name = "CMD")]
+    pub verify: Vec<String>,
+
+    /// Kill the tool (and its child processes) when an iteration exceeds this, e.g. 30m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub iteration_timeout: Option<Duration>,
+
+    /// Attempts per iteration when the tool fails transiently (retries don't use up iterations)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_attempts: Option<u32>,
+
+    /// Consecutive iterations without progress that count as a stall (0 disables; default 3)
+    #[arg(long, value_name = "N")]
+    pub stall_after: Option<u32>,
+
+    /// What to do when the run stalls: abort, pause or nudge (default abort)
+    #[arg(long, value_name = "ACTION")]
+    pub on_stall: Option<StallAction>,
+
+    /// Stop before an iteration would push the run's cost over this many USD
+    #[arg(long, value_name = "USD")]
+    pub max_cost: Option<f64>,
+
+    /// Stop before an iteration would push the run's token count over this
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<u64>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a tool iteratively on a task (the default when no subcommand is given)
+    Run(RunArgs),
+
+    /// Create a new task under .ai/tasks from a PRD template
+    Init(InitArgs),
+
+    /// List tasks under .ai/tasks with PRD progress and their last run
+    List(ListArgs),
+
+    /// Show where one task (or every task) stands
+    Status(StatusArgs),
+
+    /// Show the effective model alias table for each tool
+    Models(ModelsArgs),
+
+    /// Continue the most recent interrupted run with its remaining iterations
+    Resume(ResumeArgs),
+
+    /// Check config files, installed tools and the task layout
+    Doctor,
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    /// Task name (becomes .ai/tasks/<name>/)
+    pub name: String,
+
+    /// PRD template: feature, bugfix, refactor, or <name> from .ralph/templates/<name>.md
+    #[arg(short = 'p', long, value_name = "TEMPLATE")]
+    pub template: Option<String>,
+
+    /// Open the new PRD in $EDITOR
+    #[arg(short = 'e', long)]
+    pub edit: bool,
+}
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Print JSON instead of a table
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+pub struct StatusArgs {
+    /// Task name or directory (defaults to every task under .ai/tasks)
+    pub task: Option<String>,
+
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json: bool,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Args)]
+pub struct ModelsArgs {
+    /// Only show aliases for this tool
+    #[arg(short = 'T', long)]
+    pub tool: Option<ToolChoice>,
+}
+
+#[derive(Args)]
+pub struct ResumeArgs {
+    /// Task name or directory (defaults to the latest interrupted run of any task)
+    pub task: Option<String>,
+
+    /// Skip the confirmation prompt (required when stdin is not a terminal)
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+
+    /// Raise (or set) the run's cost budget in USD
+    #[arg(long, value_name = "USD")]
+    pub max_cost: Option<f64>,
+
+    /// Raise (or set) the run's token budget
+    #[arg(long, value_name = "N")]
+    pub max_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ToolChoice {
     /// OpenCode AI tool
-    #[value(name = "opencode", alias = "oc")]
     OpenCode,
 
     /// Claude Code CLI
-    #[value(name = "claude", alias = "cc")]
     Claude,
 
     /// OpenAI Codex CLI
-    #[value(name = "codex")]
     Codex,
+
+    /// Google Gemini CLI
+    Gemini,
+
+    /// Aider
+    Aider,
+
+    /// Generic backend defined by a `[tools.<name>]` entry with a `command`
+    Command(String),
+}
+
+impl ToolChoice {
+    /// Tools ralph knows how to drive without configuration
+    pub fn builtins() -> Vec<ToolChoice> {
+        vec![
+            ToolChoice::OpenCode,
+            ToolChoice::Claude,
+            ToolChoice::Codex,
+            ToolChoice::Gemini,
+            ToolChoice::Aider,
+        ]
+    }
+}
+
+impl FromStr for ToolChoice {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "opencode" | "oc" => Ok(ToolChoice::OpenCode),
+            "claude" | "cc" => Ok(ToolChoice::Claude),
+            "codex" => Ok(ToolChoice::Codex),
+            "gemini" | "gm" => Ok(ToolChoice::Gemini),
+            "aider" => Ok(ToolChoice::Aider),
+            "" => Err("tool name cannot be empty".to_string()),
+            _ => Ok(ToolChoice::Command(name.to_string())),
+        }
+    }
 }
 
 impl fmt::Display for ToolChoice {
@@ -55,6 +241,53 @@ impl fmt::Display for ToolChoice {
             ToolChoice::OpenCode => write!(f, "opencode"),
             ToolChoice::Claude => write!(f, "claude"),
             ToolChoice::Codex => write!(f, "codex"),
+            ToolChoice::Gemini => write!(f, "gemini"),
+            ToolChoice::Aider => write!(f, "aider"),
+            ToolChoice::Command(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_choice_parses_builtins_and_aliases() {
+        assert_eq!("opencode".parse(), Ok(ToolChoice::OpenCode));
+        assert_eq!("oc".parse(), Ok(ToolChoice::OpenCode));
+        assert_eq!("cc".parse(), Ok(ToolChoice::Claude));
+        assert_eq!("codex".parse(), Ok(ToolChoice::Codex));
+        assert_eq!("gm".parse(), Ok(ToolChoice::Gemini));
+        assert_eq!("aider".parse(), Ok(ToolChoice::Aider));
+    }
+
+    #[test]
+    fn test_tool_choice_unknown_name_is_command() {
+        assert_eq!(
+            "goose".parse(),
+            Ok(ToolChoice::Command("goose".to_string()))
+        );
+        assert!("".parse::<ToolChoice>().is_err());
+    }
+
+    #[test]
+    fn test_tool_choice_display_roundtrip() {
+        for tool in [ToolChoice::Claude, ToolChoice::Command("wrap".into())] {
+            assert_eq!(tool.to_string().parse(), Ok(tool));
         }
     }
 }
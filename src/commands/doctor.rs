@@ -0,0 +1,238 @@
+use crate::cli::ToolChoice;
+use crate::config::Config;
+use crate::error::Result;
+use crate::git;
+use crate::outcome::{EXIT_COMPLETE, EXIT_CONFIG};
+use crate::task::{TaskContext, TASKS_ROOT};
+use std::env;
+use std::fmt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Ok,
+    Warn,
+    Error,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `pad` so the label honours width flags in the report
+        f.pad(match self {
+            Level::Ok => "ok",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        })
+    }
+}
+
+struct Check {
+    level: Level,
+    message: String,
+}
+
+impl Check {
+    fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+        }
+    }
+}
+
+/// Print one line per check; the exit code is `EXIT_CONFIG` when any check is an error
+pub fn run() -> Result<u8> {
+    let mut checks = Vec::new();
+
+    let config = match Config::load() {
+        Ok(config) => {
+            checks.push(config_check(&config));
+            config
+        }
+        Err(e) => {
+            checks.push(Check::new(Level::Error, e.to_string()));
+            Config::default()
+        }
+    };
+    checks.push(git_check(Path::new(".")));
+    checks.extend(tool_checks(&config));
+    checks.extend(task_checks(Path::new(TASKS_ROOT)));
+
+    for check in &checks {
+        println!("[{:<5}] {}", check.level, check.message);
+    }
+
+    let errors = checks.iter().filter(|c| c.level == Level::Error).count();
+    let warnings = checks.iter().filter(|c| c.level == Level::Warn).count();
+    println!("\n{} error(s), {} warning(s)", errors, warnings);
+    Ok(if errors > 0 {
+        EXIT_CONFIG
+    } else {
+        EXIT_COMPLETE
+    })
+}
+
+fn config_check(config: &Config) -> Check {
+    if config.sources.is_empty() {
+        return Check::new(Level::Ok, "No config files, using defaults");
+    }
+    let sources: Vec<String> = config
+        .sources
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    Check::new(Level::Ok, format!("Config: {}", sources.join(", ")))
+}
+
+fn git_check(repo: &Path) -> Check {
+    match git::head(repo) {
+        Some(head) => Check::new(
+            Level::Ok,
+            format!("Git repository at {}", git::short(&head)),
+        ),
+        None => Check::new(
+            Level::Warn,
+            "Not a git repository with commits: checkpoints, --rollback and commit tracking are off",
+        ),
+    }
+}
+
+/// Every built-in and config-defined tool; a missing default tool is an error
+fn tool_checks(config: &Config) -> Vec<Check> {
+    let mut tools = ToolChoice::builtins();
+    tools.extend(config.command_tools());
+
+    let mut checks = Vec::new();
+    if let Some(ref tool) = config.tool {
+        if !tools.contains(tool) {
+            checks.push(Check::new(
+                Level::Error,
+                format!(
+                    "Default tool {} is neither built in nor defined with a command in [tools.{}]",
+                    tool, tool
+                ),
+            ));
+        }
+    }
+
+    for tool in tools {
+        let program = match tool {
+            ToolChoice::Command(_) => config
+                .tool(&tool)
+                .and_then(|t| t.command.as_ref())
+                .and_then(|argv| argv.first().cloned()),
+            _ => Some(tool.to_string()),
+        };
+        let is_default = config.tool.as_ref() == Some(&tool);
+        let check = match program {
+            None => Check::new(
+                Level::Error,
+                format!("{}: [tools.{}] command is empty", tool, tool),
+            ),
+            Some(program) => match find_program(&program) {
+                Some(path) => Check::new(Level::Ok, format!("{}: {}", tool, path.display())),
+                None if is_default => Check::new(
+                    Level::Error,
+                    format!(
+                        "{}: {} not found on PATH (it is the default tool)",
+                        tool, program
+                    ),
+                ),
+                None => Check::new(
+                    Level::Warn,
+                    format!("{}: {} not found on PATH", tool, program),
+                ),
+            },
+        };
+        checks.push(check);
+    }
+    checks
+}
+
+/// Each task needs a PRD for `ralph run` to pick it up
+fn task_checks(root: &Path) -> Vec<Check> {
+    if !root.is_dir() {
+        return vec![Check::new(
+            Level::Warn,
+            format!(
+                "No {} directory (create a task with `ralph init <name>`)",
+                root.display()
+            ),
+        )];
+    }
+
+    let tasks = TaskContext::list(root);
+    let mut checks = vec![Check::new(
+        Level::Ok,
+        format!("{} task(s) in {}", tasks.len(), root.display()),
+    )];
+    for dir in tasks {
+        if let Err(e) = TaskContext::find_prd_file(&dir) {
+            checks.push(Check::new(Level::Warn, e.to_string()));
+        }
+    }
+    checks
+}
+
+/// Resolve `program` like the shell would: paths as given, bare names via `$PATH`
+fn find_program(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return is_executable(&path).then_some(path);
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_program() {
+        assert!(find_program("sh").is_some());
+        assert!(find_program("ralph-test-missing-binary").is_none());
+
+        let temp = tempdir().unwrap();
+        let script = temp.path().join("tool");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        assert!(find_program(script.to_str().unwrap()).is_none());
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(find_program(script.to_str().unwrap()), Some(script));
+    }
+
+    #[test]
+    fn test_missing_default_tool_is_an_error() {
+        let config = Config {
+            tool: Some(ToolChoice::Command("mytool".into())),
+            ..Config::default()
+        };
+        let checks = tool_checks(&config);
+        assert_eq!(checks[0].level, Level::Error);
+        assert!(checks[0].message.contains("[tools.mytool]"));
+    }
+
+    #[test]
+    fn test_task_without_prd_is_a_warning() {
+        let temp = tempdir().unwrap();
+        fs::create_dir(temp.path().join("empty")).unwrap();
+        TaskContext::create(&temp.path().join("login"), "- [ ] one\n").unwrap();
+
+        let checks = task_checks(temp.path());
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].message.starts_with("2 task(s)"));
+        assert_eq!(checks[1].level, Level::Warn);
+
+        let missing = task_checks(&temp.path().join("nope"));
+        assert_eq!(missing[0].level, Level::Warn);
+    }
+}
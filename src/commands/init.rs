@@ -0,0 +1,247 @@
+use crate::cli::InitArgs;
+use crate::error::{RalphError, Result};
+use crate::task::{TaskContext, TASKS_ROOT};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Repo-provided PRD templates (`<name>.md`), checked before the built-ins
+const REPO_TEMPLATES_DIR: &str = ".ralph/templates";
+
+const FEATURE_TEMPLATE: &str = r#"# PRD: {{title}}
+
+## Goal
+<!-- What should exist when this is done, and why, in one or two sentences. -->
+
+## Background
+<!-- Current behavior, related code and anything the agent needs to know. -->
+
+## Requirements
+- [ ] First requirement
+- [ ] Second requirement
+- [ ] Tests cover the new behavior
+- [ ] README documents the new behavior
+
+## Non-Goals
+-
+
+## Implementation Notes
+-
+
+## Acceptance Criteria
+-
+"#;
+
+const BUGFIX_TEMPLATE: &str = r#"# PRD: Fix {{title}}
+
+## Goal
+<!-- The bug in one sentence, and what correct behavior looks like. -->
+
+## Current Behavior
+<!-- What happens today, including error messages or logs. -->
+
+## Expected Behavior
+<!-- What should happen instead. -->
+
+## Steps to Reproduce
+1.
+
+## Requirements
+- [ ] Add a test that reproduces the bug and fails
+- [ ] Fix the root cause
+- [ ] The new test passes along with the existing suite
+
+## Non-Goals
+- No unrelated refactoring or behavior changes.
+
+## Acceptance Criteria
+- The steps to reproduce no longer trigger the bug.
+"#;
+
+const REFACTOR_TEMPLATE: &str = r#"# PRD: Refactor {{title}}
+
+## Goal
+<!-- What the code should look like afterwards, and why that is better. -->
+
+## Motivation
+<!-- What is hard to change, understand or test today. -->
+
+## Scope
+<!-- Modules, types or functions that are in scope. -->
+
+## Requirements
+- [ ] Tests cover the current behavior before anything moves
+- [ ] First step of the refactor
+- [ ] Remove code made dead by the refactor
+
+## Non-Goals
+- No user-visible behavior changes.
+
+## Acceptance Criteria
+- The existing test suite passes unchanged.
+"#;
+
+/// Built-in PRD templates; the first is the default
+const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("feature", FEATURE_TEMPLATE),
+    ("bugfix", BUGFIX_TEMPLATE),
+    ("refactor", REFACTOR_TEMPLATE),
+];
+
+pub fn run(args: &InitArgs) -> Result<()> {
+    validate_name(&args.name)?;
+    let template = args.template.as_deref().unwrap_or(BUILTIN_TEMPLATES[0].0);
+    let prd = render(&load_template(template)?, &args.name);
+
+    let ctx = TaskContext::create(&Path::new(TASKS_ROOT).join(&args.name), &prd)?;
+    println!("Created {} ({} template)", ctx.prd_file.display(), template);
+    println!("Created {}", ctx.progress_file.display());
+
+    if args.edit {
+        open_editor(&ctx.prd_file);
+    }
+    println!(
+        "\nFill in the PRD, then run: ralph run -i 10 -t {} -T <tool>",
+        args.name
+    );
+    Ok(())
+}
+
+/// Task names become a single directory under the tasks root
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\'])
+        && !name.starts_with('-');
+    if valid {
+        Ok(())
+    } else {
+        Err(RalphError::InvalidTaskName {
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Template text: `.ralph/templates/<name>.md` if present, else a built-in
+fn load_template(name: &str) -> Result<String> {
+    let path = PathBuf::from(REPO_TEMPLATES_DIR).join(format!("{}.md", name));
+    if let Ok(text) = fs::read_to_string(&path) {
+        return Ok(text);
+    }
+    BUILTIN_TEMPLATES
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, text)| text.to_string())
+        .ok_or_else(|| RalphError::UnknownTemplate {
+            name: name.to_string(),
+            available: available_templates().join(", "),
+        })
+}
+
+/// Built-in template names plus any found in the repo templates directory
+fn available_templates() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect();
+    let repo_templates = fs::read_dir(REPO_TEMPLATES_DIR).into_iter().flatten();
+    for entry in repo_templates.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "md") {
+            if let Some(stem) = path.file_stem() {
+                let stem = stem.to_string_lossy().into_owned();
+                if !names.contains(&stem) {
+                    names.push(stem);
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Fill in `{{name}}` (the task name) and `{{title}}` (the name in title case)
+fn render(template: &str, name: &str) -> String {
+    template
+        .replace("{{name}}", name)
+        .replace("{{title}}", &title(name))
+}
+
+/// `add-login_form` -> `Add Login Form`
+fn title(name: &str) -> String {
+    name.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Open the PRD in `$EDITOR` and wait for it; problems are only warnings
+/// since the task has already been created
+fn open_editor(path: &Path) {
+    let Some(editor) = env::var("EDITOR").ok().filter(|e| !e.trim().is_empty()) else {
+        eprintln!(
+            "Warning: $EDITOR is not set; open {} yourself",
+            path.display()
+        );
+        return;
+    };
+    // Allow editors with arguments, e.g. EDITOR="code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or_default();
+    match Command::new(program).args(parts).arg(path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warning: {} exited with {}", editor, status),
+        Err(e) => eprintln!("Warning: failed to run {}: {}", editor, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prd;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title("add-login_form"), "Add Login Form");
+        assert_eq!(title("fix--crash"), "Fix Crash");
+    }
+
+    #[test]
+    fn test_render_builtin_templates() {
+        for (name, template) in BUILTIN_TEMPLATES {
+            let prd = render(template, "user-auth");
+            assert!(prd.contains("User Auth"), "{}", name);
+            assert!(!prd.contains("{{"), "{}", name);
+            // Every template starts with unchecked requirements to track progress
+            let items = prd::parse(&prd);
+            assert!(!items.is_empty(), "{}", name);
+            assert!(items.iter().all(|i| !i.checked), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_unknown_template() {
+        match load_template("epic") {
+            Err(RalphError::UnknownTemplate { available, .. }) => {
+                assert!(available.starts_with("feature, bugfix, refactor"))
+            }
+            _ => panic!("Expected UnknownTemplate error"),
+        }
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("add-login").is_ok());
+        for name in ["", "..", "a/b", "-x"] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod doctor;
+pub mod init;
+pub mod models;
+pub mod status;
@@ -0,0 +1,48 @@
+use crate::cli::{ModelsArgs, ToolChoice};
+use crate::config::Config;
+use crate::error::Result;
+use crate::models::{default_model, AliasSource, AliasTable};
+
+pub fn run(args: &ModelsArgs) -> Result<()> {
+    let config = Config::load()?;
+
+    let tools = match args.tool {
+        Some(ref tool) => vec![tool.clone()],
+        None => {
+            let mut tools = ToolChoice::builtins();
+            tools.extend(config.command_tools());
+            tools
+        }
+    };
+
+    for (i, tool) in tools.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{}:", tool);
+        if let Some(model) = default_model(tool) {
+            println!("  (default model: {})", model);
+        }
+
+        let entries = AliasTable::new(tool, &config).entries();
+        if entries.is_empty() {
+            println!("  (no aliases)");
+            continue;
+        }
+
+        let alias_width = entries.iter().map(|e| e.alias.len()).max().unwrap_or(0);
+        let model_width = entries.iter().map(|e| e.model.len()).max().unwrap_or(0);
+        for entry in entries {
+            let source = match entry.source {
+                AliasSource::BuiltIn => "built-in".to_string(),
+                AliasSource::Config(path) => path.display().to_string(),
+            };
+            println!(
+                "  {:<alias_width$}  {:<model_width$}  {}",
+                entry.alias, entry.model, source
+            );
+        }
+    }
+
+    Ok(())
+}
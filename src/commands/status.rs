@@ -0,0 +1,175 @@
+use crate::cli::{ListArgs, StatusArgs};
+use crate::error::Result;
+use crate::outcome::RunOutcome;
+use crate::status::TaskStatus;
+use crate::task::{TaskContext, TASKS_ROOT};
+use serde::Serialize;
+use std::path::Path;
+
+pub fn list(args: &ListArgs) -> Result<()> {
+    let tasks: Vec<TaskStatus> = TaskContext::list(Path::new(TASKS_ROOT))
+        .iter()
+        .map(|dir| TaskStatus::load(dir))
+        .collect();
+
+    if args.json {
+        print_json(&tasks);
+        return Ok(());
+    }
+    if tasks.is_empty() {
+        println!(
+            "No tasks in {} (create one with `ralph init <name>`)",
+            TASKS_ROOT
+        );
+        return Ok(());
+    }
+
+    let rows: Vec<[String; 5]> = tasks.iter().map(table_row).collect();
+    let header = ["TASK", "PRD", "LAST PROGRESS", "LAST RUN", "DONE"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+    Ok(())
+}
+
+fn table_row(task: &TaskStatus) -> [String; 5] {
+    [
+        task.name.clone(),
+        match (&task.prd_file, task.progress) {
+            (None, _) => "no PRD".to_string(),
+            (Some(_), Some(progress)) => progress.to_string(),
+            (Some(_), None) => "-".to_string(),
+        },
+        task.last_progress_entry
+            .map_or_else(|| "-".to_string(), |date| date.to_string()),
+        task.last_run.as_ref().map_or_else(
+            || "-".to_string(),
+            |run| {
+                format!(
+                    "{} ({})",
+                    run.outcome_label(),
+                    run.started_at.format("%Y-%m-%d")
+                )
+            },
+        ),
+        if task.completed { "yes" } else { "no" }.to_string(),
+    ]
+}
+
+pub fn status(args: &StatusArgs) -> Result<()> {
+    let dirs = match args.task {
+        Some(ref task) => vec![TaskContext::resolve_directory(task)?],
+        None => TaskContext::list(Path::new(TASKS_ROOT)),
+    };
+    let tasks: Vec<TaskStatus> = dirs.iter().map(|dir| TaskStatus::load(dir)).collect();
+
+    if args.json {
+        match (&args.task, tasks.first()) {
+            (Some(_), Some(task)) => print_json(task),
+            _ => print_json(&tasks),
+        }
+        return Ok(());
+    }
+    if tasks.is_empty() {
+        println!(
+            "No tasks in {} (create one with `ralph init <name>`)",
+            TASKS_ROOT
+        );
+    }
+
+    for (i, task) in tasks.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_status(task);
+    }
+    Ok(())
+}
+
+fn print_status(task: &TaskStatus) {
+    println!("{}:", task.name);
+    println!("  Task dir:      {}", task.dir.display());
+    match (&task.prd_file, task.progress) {
+        (Some(prd), Some(progress)) => {
+            println!(
+                "  PRD file:      {} ({} items done)",
+                prd.display(),
+                progress
+            )
+        }
+        (Some(prd), None) => println!("  PRD file:      {} (no task list)", prd.display()),
+        (None, _) => println!("  PRD file:      none"),
+    }
+    if let Some(ref item) = task.next_item {
+        println!("  Next item:     {}", item);
+    }
+    if let Some(date) = task.last_progress_entry {
+        println!("  Last progress: {}", date);
+    }
+    println!("  Runs:          {}", task.runs);
+    if let Some(ref run) = task.last_run {
+        let model = run
+            .model
+            .as_deref()
+            .map_or_else(String::new, |model| format!(" / {}", model));
+        println!(
+            "  Last run:      {}, {}{}, started {}",
+            run.summary(),
+            run.tool,
+            model,
+            run.started_at.format("%Y-%m-%d %H:%M")
+        );
+        if let Some(RunOutcome::Stalled { ref reason, .. }) = run.outcome {
+            println!("  Stalled:       {}", reason);
+        }
+        println!("  Run dir:       {}", run.dir.display());
+    }
+    println!(
+        "  Completed:     {}",
+        if task.completed { "yes" } else { "no" }
+    );
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("status serialization cannot fail")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prd::PrdProgress;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_load_task_without_runs() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path().join("login");
+        TaskContext::create(&dir, "# PRD\n- [x] one\n- [ ] two\n").unwrap();
+
+        let status = TaskStatus::load(&dir);
+        assert_eq!(status.name, "login");
+        assert_eq!(status.progress, Some(PrdProgress { done: 1, total: 2 }));
+        assert_eq!(status.next_item.as_deref(), Some("two"));
+        assert_eq!(status.runs, 0);
+        assert!(status.last_run.is_none());
+        assert!(!status.completed);
+        assert_eq!(
+            table_row(&status),
+            ["login", "1/2", "-", "-", "no"].map(String::from)
+        );
+    }
+}
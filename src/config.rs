@@ -0,0 +1,991 @@
+use crate::cli::{RunArgs, ToolChoice};
+use crate::error::{RalphError, Result};
+use crate::manifest::RunManifest;
+use crate::models::{default_model, model_price, AliasTable, ModelPrice};
+use crate::retry::RetryPolicy;
+use crate::stall::{StallAction, StallPolicy};
+use crate::tools::{CommandSpec, COMPLETION_MARKER, PLACEHOLDERS};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Repo-level config file, looked up in the current directory
+pub const REPO_CONFIG_FILE: &str = ".ralph.toml";
+
+/// Uncommitted personal overrides of the repo-level config
+pub const LOCAL_CONFIG_FILE: &str = ".ralph.local.toml";
+
+/// Settings read from one config file. Every field is optional so that
+/// layers can be merged, later layers overriding earlier ones.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub tool: Option<ToolChoice>,
+    pub model: Option<String>,
+    pub iterations: Option<u32>,
+    /// Reset the working tree to its pre-iteration checkpoint when an iteration fails
+    pub rollback: Option<bool>,
+    /// Have ralph pick the next PRD item instead of leaving it to the agent
+    pub select_item: Option<bool>,
+    /// Failed iterations in a row before moving up a `model` chain
+    pub escalate_after: Option<u32>,
+    /// Commands that must pass before a completion claim is accepted
+    pub verify: Option<Vec<String>>,
+    /// Kill the tool when a single iteration runs longer than this (e.g. "30m")
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub iteration_timeout: Option<Duration>,
+    /// Stop before an iteration would push the run's cost over this many USD
+    pub max_cost: Option<f64>,
+    /// Stop before an iteration would push the run's token count over this
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub stall: StallConfig,
+    #[serde(default)]
+    pub rules: RulesConfig,
+    #[serde(default)]
+    pub tools: HashMap<String, ToolConfig>,
+    /// Model aliases per tool (`[aliases.<tool>]`), merged over the built-ins
+    #[serde(default)]
+    pub aliases: HashMap<String, HashMap<String, ConfigAlias>>,
+    /// Per-model price overrides in USD per million tokens (`[prices.<model>]`)
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+    /// Files that contributed to this config, in load order
+    #[serde(skip)]
+    pub sources: Vec<PathBuf>,
+}
+
+/// Per-tool defaults (`[tools.<name>]`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolConfig {
+    pub model: Option<String>,
+    pub variant: Option<String>,
+    /// argv of a config-defined tool; `{prompt}`, `{model}`, `{variant}` and
+    /// `{task_dir}` are substituted
+    pub command: Option<Vec<String>>,
+    /// Completion marker printed by a config-defined tool
+    pub completion: Option<String>,
+    /// Regex matched against a config-defined tool's output to detect completion
+    pub completion_regex: Option<String>,
+}
+
+/// Retry policy overrides (`[retry]`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    pub max_attempts: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub backoff: Option<Duration>,
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_backoff: Option<Duration>,
+}
+
+/// Stall detection overrides (`[stall]`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StallConfig {
+    /// Consecutive iterations without progress that count as a stall (0 disables)
+    pub after: Option<u32>,
+    /// `abort`, `pause` or `nudge`
+    pub action: Option<StallAction>,
+}
+
+/// Rules files attached to the prompt (`[rules]`)
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RulesConfig {
+    /// Look for AGENTS.md / CLAUDE.md in the usual places (default true)
+    pub discover: Option<bool>,
+    /// Extra rules files; `~` expands to the home directory
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Rules files to leave out, discovered or included
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// A model alias defined in a config file, remembering which file it came from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "String")]
+pub struct ConfigAlias {
+    pub model: String,
+    pub source: PathBuf,
+}
+
+impl From<String> for ConfigAlias {
+    fn from(model: String) -> Self {
+        Self {
+            model,
+            source: PathBuf::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Load and merge the user-level, repo-level and local config files
+    pub fn load() -> Result<Self> {
+        let mut config = Self::default();
+        for path in Self::layer_paths() {
+            if path.is_file() {
+                config.merge(Self::load_file(&path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| RalphError::ConfigRead {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        let mut config: Self = toml::from_str(&content).map_err(|e| RalphError::ConfigParse {
+            path: path.to_path_buf(),
+            source: e,
+        })?;
+        for alias in config.aliases.values_mut().flat_map(|a| a.values_mut()) {
+            alias.source = path.to_path_buf();
+        }
+        config.sources.push(path.to_path_buf());
+        Ok(config)
+    }
+
+    /// Overlay `other` on top of `self`
+    pub fn merge(&mut self, other: Config) {
+        if other.tool.is_some() {
+            self.tool = other.tool;
+        }
+        if other.model.is_some() {
+            self.model = other.model;
+        }
+        if other.iterations.is_some() {
+            self.iterations = other.iterations;
+        }
+        if other.rollback.is_some() {
+            self.rollback = other.rollback;
+        }
+        if other.select_item.is_some() {
+            self.select_item = other.select_item;
+        }
+        if other.escalate_after.is_some() {
+            self.escalate_after = other.escalate_after;
+        }
+        if other.verify.is_some() {
+            self.verify = other.verify;
+        }
+        if other.iteration_timeout.is_some() {
+            self.iteration_timeout = other.iteration_timeout;
+        }
+        if other.max_cost.is_some() {
+            self.max_cost = other.max_cost;
+        }
+        if other.max_tokens.is_some() {
+            self.max_tokens = other.max_tokens;
+        }
+        if other.retry.max_attempts.is_some() {
+            self.retry.max_attempts = other.retry.max_attempts;
+        }
+        if other.retry.backoff.is_some() {
+            self.retry.backoff = other.retry.backoff;
+        }
+        if other.retry.max_backoff.is_some() {
+            self.retry.max_backoff = other.retry.max_backoff;
+        }
+        if other.stall.after.is_some() {
+            self.stall.after = other.stall.after;
+        }
+        if other.stall.action.is_some() {
+            self.stall.action = other.stall.action;
+        }
+        if other.rules.discover.is_some() {
+            self.rules.discover = other.rules.discover;
+        }
+        self.rules.include.extend(other.rules.include);
+        self.rules.exclude.extend(other.rules.exclude);
+        for (name, tool) in other.tools {
+            let entry = self.tools.entry(name).or_default();
+            if tool.model.is_some() {
+                entry.model = tool.model;
+            }
+            if tool.variant.is_some() {
+                entry.variant = tool.variant;
+            }
+            if tool.command.is_some() {
+                entry.command = tool.command;
+            }
+            if tool.completion.is_some() {
+                entry.completion = tool.completion;
+            }
+            if tool.completion_regex.is_some() {
+                entry.completion_regex = tool.completion_regex;
+            }
+        }
+        for (name, aliases) in other.aliases {
+            self.aliases.entry(name).or_default().extend(aliases);
+        }
+        self.prices.extend(other.prices);
+        self.sources.extend(other.sources);
+    }
+
+    pub fn tool(&self, tool: &ToolChoice) -> Option<&ToolConfig> {
+        self.tools.get(&tool.to_string())
+    }
+
+    /// Names of the config-defined tools (`[tools.<name>]` with a `command`)
+    pub fn command_tools(&self) -> Vec<ToolChoice> {
+        let mut names: Vec<&String> = self
+            .tools
+            .iter()
+            .filter(|(_, tool)| tool.command.is_some())
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| ToolChoice::Command(name.clone()))
+            .collect()
+    }
+
+    pub fn aliases(&self, tool: &ToolChoice) -> Option<&HashMap<String, ConfigAlias>> {
+        self.aliases.get(&tool.to_string())
+    }
+
+    fn layer_paths() -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        if let Some(dir) = user_config_dir() {
+            paths.push(dir.join("ralph").join("config.toml"));
+        }
+        paths.push(PathBuf::from(REPO_CONFIG_FILE));
+        paths.push(PathBuf::from(LOCAL_CONFIG_FILE));
+        paths
+    }
+}
+
+/// Parse a duration such as `90`, `90s`, `15m` or `2h` (bare numbers are seconds)
+pub fn parse_duration(input: &str) -> std::result::Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let invalid = || format!("invalid duration '{}'", input);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "" | "s" => Some(number),
+        "m" => number.checked_mul(60),
+        "h" => number.checked_mul(60 * 60),
+        _ => {
+            return Err(format!(
+                "invalid duration unit in '{}' (use s, m or h)",
+                input
+            ))
+        }
+    };
+    seconds.map(Duration::from_secs).ok_or_else(invalid)
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<Duration>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_duration(&value)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn user_config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+}
+
+/// Fully resolved settings for a run: CLI flags over config over built-in defaults
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub tool: ToolChoice,
+    pub model: Option<String>,
+    pub variant: Option<String>,
+    pub iterations: u32,
+    pub rollback: bool,
+    pub select_item: bool,
+    pub verify: Vec<String>,
+    pub iteration_timeout: Option<Duration>,
+    pub retry: RetryPolicy,
+    pub stall: StallPolicy,
+    pub max_cost: Option<f64>,
+    pub max_tokens: Option<u64>,
+    /// Price of `model`, used when the tool does not report cost itself
+    pub price: Option<ModelPrice>,
+    /// Models to escalate through, cheapest first (empty unless `model` lists several)
+    pub model_chain: Vec<ModelStep>,
+    /// Failed iterations in a row before escalating to the next model
+    pub escalate_after: u32,
+    /// Set when `tool` is a config-defined command tool
+    pub command: Option<CommandSpec>,
+}
+
+/// One model of a `--model a,b,c` escalation chain
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelStep {
+    pub model: String,
+    pub price: Option<ModelPrice>,
+}
+
+impl ModelStep {
+    fn new(model: &str, config: &Config) -> Self {
+        Self {
+            model: model.to_string(),
+            price: model_price(model, config),
+        }
+    }
+}
+
+/// Default for `escalate_after`
+const DEFAULT_ESCALATE_AFTER: u32 = 2;
+
+impl RunConfig {
+    /// Completion marker the prompt asks for, matching what the tool detects
+    pub fn completion_marker(&self) -> String {
+        self.command.as_ref().map_or_else(
+            || COMPLETION_MARKER.to_string(),
+            CommandSpec::completion_marker,
+        )
+    }
+
+    pub fn resolve(cli: &RunArgs, config: &Config) -> Result<Self> {
+        let tool = cli.tool.clone().or_else(|| config.tool.clone());
+        let tool = tool.ok_or(RalphError::MissingSetting {
+            flag: "--tool",
+            key: "tool",
+        })?;
+
+        let iterations = cli.iterations.or(config.iterations);
+        let iterations = iterations.ok_or(RalphError::MissingSetting {
+            flag: "--iterations",
+            key: "iterations",
+        })?;
+
+        let tool_config = config.tool(&tool);
+
+        let aliases = AliasTable::new(&tool, config);
+        let models: Vec<String> = cli
+            .model
+            .clone()
+            .or_else(|| tool_config.and_then(|t| t.model.clone()))
+            .or_else(|| config.model.clone())
+            .iter()
+            .flat_map(|m| m.split(','))
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(|m| aliases.resolve(m))
+            .collect();
+        let model = models.first().cloned().or_else(|| default_model(&tool));
+        let model_chain = if models.len() > 1 {
+            models.iter().map(|m| ModelStep::new(m, config)).collect()
+        } else {
+            Vec::new()
+        };
+
+        let variant = cli
+            .variant
+            .clone()
+            .or_else(|| tool_config.and_then(|t| t.variant.clone()));
+
+        if variant.is_some() && !matches!(tool, ToolChoice::OpenCode | ToolChoice::Command(_)) {
+            return Err(RalphError::InvalidToolOption {
+                tool: tool.to_string(),
+            });
+        }
+
+        let price = model.as_deref().and_then(|m| model_price(m, config));
+
+        let command = match tool {
+            ToolChoice::Command(ref name) => Some(Self::command_spec(name, tool_config)?),
+            _ => None,
+        };
+
+        Ok(Self {
+            tool,
+            model,
+            variant,
+            iterations,
+            rollback: cli.rollback || config.rollback.unwrap_or(false),
+            select_item: cli.select_item || config.select_item.unwrap_or(false),
+            verify: if cli.verify.is_empty() {
+                config.verify.clone().unwrap_or_default()
+            } else {
+                cli.verify.clone()
+            },
+            iteration_timeout: cli.iteration_timeout.or(config.iteration_timeout),
+            retry: Self::retry_policy(cli, config),
+            stall: Self::stall_policy(cli, config),
+            max_cost: cli.max_cost.or(config.max_cost),
+            max_tokens: cli.max_tokens.or(config.max_tokens),
+            price,
+            model_chain,
+            escalate_after: cli
+                .escalate_after
+                .or(config.escalate_after)
+                .unwrap_or(DEFAULT_ESCALATE_AFTER)
+                .max(1),
+            command,
+        })
+    }
+
+    /// Settings for `ralph resume`: what was run and how (verification,
+    /// timeouts, budgets...) come from the manifest. Manifests without
+    /// recorded settings fall back to the current config.
+    pub fn for_resume(manifest: &RunManifest, config: &Config) -> Result<Self> {
+        let cli = RunArgs {
+            tool: Some(manifest.tool.clone()),
+            iterations: Some(manifest.iterations_planned),
+            ..RunArgs::default()
+        };
+        let mut run = Self::resolve(&cli, config)?;
+        if let Some(ref settings) = manifest.settings {
+            run.rollback = settings.rollback;
+            run.select_item = settings.select_item;
+            run.verify = settings.verify.clone();
+            run.iteration_timeout = settings.iteration_timeout_secs.map(Duration::from_secs);
+            run.retry.max_attempts = settings.max_attempts.max(1);
+            run.stall = StallPolicy {
+                after: settings.stall_after,
+                action: settings.on_stall,
+            };
+            run.escalate_after = settings.escalate_after.max(1);
+            run.max_cost = settings.max_cost;
+            run.max_tokens = settings.max_tokens;
+        }
+        let model_chain: Vec<ModelStep> = manifest
+            .model_chain
+            .iter()
+            .map(|m| ModelStep::new(m, config))
+            .collect();
+        // An escalated run continues on the model it had switched to
+        let model = match manifest.escalation_level.and_then(|l| model_chain.get(l)) {
+            Some(step) => Some(step.model.clone()),
+            None => manifest.model.clone(),
+        };
+        Ok(Self {
+            price: model.as_deref().and_then(|m| model_price(m, config)),
+            model,
+            variant: manifest.variant.clone(),
+            model_chain,
+            ..run
+        })
+    }
+
+    fn command_spec(name: &str, tool_config: Option<&ToolConfig>) -> Result<CommandSpec> {
+        let tool_config = tool_config.filter(|t| t.command.as_ref().is_some_and(|c| !c.is_empty()));
+        let Some(tool_config) = tool_config else {
+            return Err(RalphError::UnknownTool {
+                name: name.to_string(),
+            });
+        };
+
+        // The program itself must not depend on a value that may be unset
+        let argv = tool_config.command.clone().unwrap_or_default();
+        if PLACEHOLDERS.iter().any(|p| argv[0].contains(p)) {
+            return Err(RalphError::InvalidSetting {
+                key: format!("tools.{}.command", name),
+                message: "the first entry must be the program to run, without placeholders"
+                    .to_string(),
+            });
+        }
+
+        let completion_regex = match tool_config.completion_regex {
+            Some(ref pattern) => {
+                Some(Regex::new(pattern).map_err(|e| RalphError::InvalidSetting {
+                    key: format!("tools.{}.completion_regex", name),
+                    message: e.to_string(),
+                })?)
+            }
+            None => None,
+        };
+
+        Ok(CommandSpec {
+            argv,
+            completion: tool_config.completion.clone(),
+            completion_regex,
+        })
+    }
+
+    fn stall_policy(cli: &RunArgs, config: &Config) -> StallPolicy {
+        let default = StallPolicy::default();
+        StallPolicy {
+            after: cli
+                .stall_after
+                .or(config.stall.after)
+                .unwrap_or(default.after),
+            action: cli
+                .on_stall
+                .or(config.stall.action)
+                .unwrap_or(default.action),
+        }
+    }
+
+    fn retry_policy(cli: &RunArgs, config: &Config) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: cli
+                .max_attempts
+                .or(config.retry.max_attempts)
+                .unwrap_or(default.max_attempts)
+                .max(1),
+            backoff: config.retry.backoff.unwrap_or(default.backoff),
+            max_backoff: config.retry.max_backoff.unwrap_or(default.max_backoff),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::RunSettings;
+    use tempfile::tempdir;
+
+    fn parse(content: &str) -> Config {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_load_file_parses_all_fields() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(".ralph.toml");
+        fs::write(
+            &path,
+            "tool = \"oc\"\niterations = 7\nmodel = \"sonnet\"\n\n[tools.opencode]\nvariant = \"fast\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_file(&path).unwrap();
+        assert!(matches!(config.tool, Some(ToolChoice::OpenCode)));
+        assert_eq!(config.iterations, Some(7));
+        assert_eq!(config.model.as_deref(), Some("sonnet"));
+        assert_eq!(config.tools["opencode"].variant.as_deref(), Some("fast"));
+        assert_eq!(config.sources, vec![path]);
+    }
+
+    #[test]
+    fn test_load_file_rejects_unknown_keys() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(".ralph.toml");
+        fs::write(&path, "iteratons = 3\n").unwrap();
+
+        match Config::load_file(&path) {
+            Err(RalphError::ConfigParse { path: p, .. }) => assert_eq!(p, path),
+            _ => panic!("Expected ConfigParse error"),
+        }
+    }
+
+    #[test]
+    fn test_merge_later_layer_wins() {
+        let mut config = parse(
+            "tool = \"claude\"\niterations = 3\n\n[tools.opencode]\nmodel = \"opus\"\nvariant = \"fast\"\n",
+        );
+        config.merge(parse(
+            "iterations = 10\n\n[tools.opencode]\nmodel = \"haiku\"\n",
+        ));
+
+        assert!(matches!(config.tool, Some(ToolChoice::Claude)));
+        assert_eq!(config.iterations, Some(10));
+        assert_eq!(config.tools["opencode"].model.as_deref(), Some("haiku"));
+        assert_eq!(config.tools["opencode"].variant.as_deref(), Some("fast"));
+    }
+
+    #[test]
+    fn test_load_file_records_alias_source() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(".ralph.toml");
+        fs::write(&path, "[aliases.claude]\nbig = \"claude-opus-4-5\"\n").unwrap();
+
+        let config = Config::load_file(&path).unwrap();
+        let alias = &config.aliases(&ToolChoice::Claude).unwrap()["big"];
+        assert_eq!(alias.model, "claude-opus-4-5");
+        assert_eq!(alias.source, path);
+    }
+
+    #[test]
+    fn test_merge_aliases_per_entry() {
+        let mut config = parse("[aliases.codex]\nfast = \"a\"\nslow = \"b\"\n");
+        config.merge(parse("[aliases.codex]\nfast = \"c\"\n"));
+
+        let aliases = config.aliases(&ToolChoice::Codex).unwrap();
+        assert_eq!(aliases["fast"].model, "c");
+        assert_eq!(aliases["slow"].model, "b");
+    }
+
+    #[test]
+    fn test_resolve_uses_config_alias() {
+        let config = parse("tool = \"claude\"\niterations = 1\nmodel = \"big\"\n\n[aliases.claude]\nbig = \"claude-opus-4-5\"\n");
+
+        let run = RunConfig::resolve(&RunArgs::default(), &config).unwrap();
+        assert_eq!(run.model.as_deref(), Some("claude-opus-4-5"));
+    }
+
+    #[test]
+    fn test_resolve_cli_overrides_config() {
+        let config = parse("tool = \"claude\"\niterations = 3\nmodel = \"opus\"\n");
+        let cli = RunArgs {
+            tool: Some(ToolChoice::OpenCode),
+            iterations: Some(5),
+            model: Some("sonnet".into()),
+            ..RunArgs::default()
+        };
+
+        let run = RunConfig::resolve(&cli, &config).unwrap();
+        assert!(matches!(run.tool, ToolChoice::OpenCode));
+        assert_eq!(run.iterations, 5);
+        assert_eq!(run.model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+    }
+
+    #[test]
+    fn test_resolve_tool_section_over_top_level_model() {
+        let config = parse("tool = \"oc\"\niterations = 3\nmodel = \"opus\"\n[tools.opencode]\nmodel = \"haiku\"\n");
+
+        let run = RunConfig::resolve(&RunArgs::default(), &config).unwrap();
+        assert_eq!(run.model.as_deref(), Some("anthropic/claude-haiku-4-5"));
+    }
+
+    #[test]
+    fn test_resolve_model_chain() {
+        let config = parse("tool = \"opencode\"\niterations = 1\nescalate_after = 3\n");
+        let cli = RunArgs {
+            model: Some("sonnet, opus".into()),
+            ..RunArgs::default()
+        };
+
+        let run = RunConfig::resolve(&cli, &config).unwrap();
+        assert_eq!(run.model.as_deref(), Some("anthropic/claude-sonnet-4-5"));
+        let chain: Vec<&str> = run.model_chain.iter().map(|s| s.model.as_str()).collect();
+        assert_eq!(
+            chain,
+            ["anthropic/claude-sonnet-4-5", "anthropic/claude-opus-4-5"]
+        );
+        assert!(run.model_chain[1].price.is_some());
+        assert_eq!(run.escalate_after, 3);
+
+        let run = RunConfig::resolve(&RunArgs::default(), &config).unwrap();
+        assert!(run.model_chain.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_default_model() {
+        let config = parse("tool = \"opencode\"\niterations = 1\n");
+
+        let run = RunConfig::resolve(&RunArgs::default(), &config).unwrap();
+        assert_eq!(run.model, default_model(&ToolChoice::OpenCode));
+    }
+
+    #[test]
+    fn test_resolve_select_item_from_config() {
+        let config = parse("tool = \"claude\"\niterations = 1\nselect_item = true\n");
+        assert!(
+            RunConfig::resolve(&RunArgs::default(), &config)
+                .unwrap()
+                .select_item
+        );
+    }
+
+    #[test]
+    fn test_resolve_rollback_from_config_or_cli() {
+        let config = parse("tool = \"claude\"\niterations = 1\n");
+        let mut cli = RunArgs::default();
+        assert!(!RunConfig::resolve(&cli, &config).unwrap().rollback);
+
+        cli.rollback = true;
+        assert!(RunConfig::resolve(&cli, &config).unwrap().rollback);
+
+        let config = parse("tool = \"claude\"\niterations = 1\nrollback = true\n");
+        assert!(
+            RunConfig::resolve(&RunArgs::default(), &config)
+                .unwrap()
+                .rollback
+        );
+    }
+
+    #[test]
+    fn test_resolve_cli_verify_replaces_config() {
+        let config = parse(
+            "tool = \"claude\"\niterations = 1\nverify = [\"cargo test\", \"cargo clippy\"]\n",
+        );
+        assert_eq!(
+            RunConfig::resolve(&RunArgs::default(), &config)
+                .unwrap()
+                .verify,
+            vec!["cargo test", "cargo clippy"]
+        );
+
+        let cli = RunArgs {
+            verify: vec!["make check".into()],
+            ..RunArgs::default()
+        };
+        assert_eq!(
+            RunConfig::resolve(&cli, &config).unwrap().verify,
+            vec!["make check"]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert_eq!(
+            parse_duration("999999999999999999h"),
+            Err("invalid duration '999999999999999999h'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_load_iteration_timeout() {
+        let config = parse("iteration_timeout = \"30m\"\n");
+        assert_eq!(config.iteration_timeout, Some(Duration::from_secs(1800)));
+
+        assert!(toml::from_str::<Config>("iteration_timeout = \"soon\"\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_retry_policy() {
+        let config = parse("tool = \"claude\"\niterations = 1\n");
+        assert_eq!(
+            RunConfig::resolve(&RunArgs::default(), &config)
+                .unwrap()
+                .retry,
+            RetryPolicy::default()
+        );
+
+        let config = parse(
+            "tool = \"claude\"\niterations = 1\n\n[retry]\nmax_attempts = 3\nbackoff = \"2s\"\n",
+        );
+        let retry = RunConfig::resolve(&RunArgs::default(), &config)
+            .unwrap()
+            .retry;
+        assert_eq!(retry.max_attempts, 3);
+        assert_eq!(retry.backoff, Duration::from_secs(2));
+
+        let cli = RunArgs {
+            max_attempts: Some(5),
+            ..RunArgs::default()
+        };
+        assert_eq!(
+            RunConfig::resolve(&cli, &config)
+                .unwrap()
+                .retry
+                .max_attempts,
+            5
+        );
+    }
+
+    #[test]
+    fn test_resolve_stall_policy() {
+        let config =
+            parse("tool = \"claude\"\niterations = 1\n\n[stall]\nafter = 5\naction = \"pause\"\n");
+        let stall = RunConfig::resolve(&RunArgs::default(), &config)
+            .unwrap()
+            .stall;
+        assert_eq!(stall.after, 5);
+        assert_eq!(stall.action, StallAction::Pause);
+
+        let cli = RunArgs {
+            on_stall: Some(StallAction::Nudge),
+            ..RunArgs::default()
+        };
+        let stall = RunConfig::resolve(&cli, &config).unwrap().stall;
+        assert_eq!(stall.action, StallAction::Nudge);
+
+        assert!(toml::from_str::<Config>("[stall]\naction = \"retry\"\n").is_err());
+    }
+
+    #[test]
+    fn test_resolve_missing_iterations() {
+        let config = parse("tool = \"claude\"\n");
+
+        match RunConfig::resolve(&RunArgs::default(), &config) {
+            Err(RalphError::MissingSetting { key, .. }) => assert_eq!(key, "iterations"),
+            _ => panic!("Expected MissingSetting error"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_rejects_variant_for_non_opencode() {
+        let config = parse("tool = \"codex\"\niterations = 1\n[tools.codex]\nvariant = \"fast\"\n");
+
+        assert!(matches!(
+            RunConfig::resolve(&RunArgs::default(), &config),
+            Err(RalphError::InvalidToolOption { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolve_command_tool() {
+        let config = parse(
+            r#"
+tool = "goose"
+iterations = 2
+[tools.goose]
+command = ["goose", "--message", "{prompt}", "--model={model}"]
+model = "sonnet"
+variant = "high"
+completion_regex = "DONE$"
+"#,
+        );
+
+        let run = RunConfig::resolve(&RunArgs::default(), &config).unwrap();
+        assert_eq!(run.tool, ToolChoice::Command("goose".into()));
+        assert_eq!(run.model.as_deref(), Some("sonnet"));
+        assert_eq!(run.variant.as_deref(), Some("high"));
+        let spec = run.command.unwrap();
+        assert_eq!(spec.argv[0], "goose");
+        assert!(spec.completion_regex.unwrap().is_match("all DONE"));
+    }
+
+    #[test]
+    fn test_resolve_unknown_tool() {
+        let config = parse("tool = \"mystery\"\niterations = 1\n[tools.mystery]\nmodel = \"x\"\n");
+
+        match RunConfig::resolve(&RunArgs::default(), &config) {
+            Err(RalphError::UnknownTool { name }) => assert_eq!(name, "mystery"),
+            _ => panic!("Expected UnknownTool error"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_invalid_completion_regex() {
+        let config = parse(
+            "tool = \"wrap\"\niterations = 1\n[tools.wrap]\ncommand = [\"wrap\"]\ncompletion_regex = \"(\"\n",
+        );
+
+        match RunConfig::resolve(&RunArgs::default(), &config) {
+            Err(RalphError::InvalidSetting { key, .. }) => {
+                assert_eq!(key, "tools.wrap.completion_regex")
+            }
+            _ => panic!("Expected InvalidSetting error"),
+        }
+    }
+
+    #[test]
+    fn test_resolve_command_starting_with_placeholder() {
+        let config = parse(
+            "tool = \"wrap\"\niterations = 1\n[tools.wrap]\ncommand = [\"{model}\", \"{prompt}\"]\n",
+        );
+
+        match RunConfig::resolve(&RunArgs::default(), &config) {
+            Err(RalphError::InvalidSetting { key, .. }) => assert_eq!(key, "tools.wrap.command"),
+            _ => panic!("Expected InvalidSetting error"),
+        }
+    }
+
+    #[test]
+    fn test_command_tools_lists_only_tools_with_command() {
+        let config = parse(
+            "[tools.zed]\ncommand = [\"zed\"]\n[tools.claude]\nmodel = \"opus\"\n[tools.goose]\ncommand = [\"goose\"]\n",
+        );
+
+        assert_eq!(
+            config.command_tools(),
+            vec![
+                ToolChoice::Command("goose".into()),
+                ToolChoice::Command("zed".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_budget_and_price() {
+        let config = parse(
+            "tool = \"codex\"\niterations = 1\nmodel = \"my-model\"\nmax_cost = 2.5\n\n[prices.my-model]\ninput = 1.0\noutput = 2.0\n",
+        );
+        let cli = RunArgs {
+            max_tokens: Some(1000),
+            ..RunArgs::default()
+        };
+
+        let run = RunConfig::resolve(&cli, &config).unwrap();
+        assert_eq!(run.max_cost, Some(2.5));
+        assert_eq!(run.max_tokens, Some(1000));
+        assert_eq!(run.price.unwrap().output, 2.0);
+    }
+
+    #[test]
+    fn test_for_resume_restores_recorded_settings() {
+        let cli = RunArgs {
+            tool: Some(ToolChoice::Claude),
+            iterations: Some(5),
+            rollback: true,
+            select_item: true,
+            verify: vec!["cargo test".into()],
+            iteration_timeout: Some(Duration::from_secs(600)),
+            max_attempts: Some(3),
+            stall_after: Some(0),
+            on_stall: Some(StallAction::Nudge),
+            escalate_after: Some(4),
+            max_cost: Some(1.5),
+            ..RunArgs::default()
+        };
+        let original = RunConfig::resolve(&cli, &Config::default()).unwrap();
+        let manifest = RunManifest {
+            task_dir: PathBuf::from("task"),
+            prd_file: PathBuf::from("task/PRD.md"),
+            progress_file: PathBuf::from("task/PROGRESS.md"),
+            tool: ToolChoice::Claude,
+            model: original.model.clone(),
+            model_chain: Vec::new(),
+            escalation_level: None,
+            variant: None,
+            iterations_planned: 5,
+            settings: Some(RunSettings::of(&original)),
+            started_at: chrono::Utc::now(),
+            finished_at: None,
+            outcome: None,
+            iterations: Vec::new(),
+        };
+
+        // Config changed since the run started; the recorded flags still win
+        let config = parse("verify = [\"make check\"]\nmax_cost = 9.0\n");
+        let run = RunConfig::for_resume(&manifest, &config).unwrap();
+        assert!(run.rollback && run.select_item);
+        assert_eq!(run.verify, ["cargo test"]);
+        assert_eq!(run.iteration_timeout, Some(Duration::from_secs(600)));
+        assert_eq!(run.retry.max_attempts, 3);
+        assert_eq!(run.stall, original.stall);
+        assert_eq!(run.escalate_after, 4);
+        assert_eq!(run.max_cost, Some(1.5));
+
+        let old = RunManifest {
+            settings: None,
+            ..manifest.clone()
+        };
+        assert_eq!(
+            RunConfig::for_resume(&old, &config).unwrap().verify,
+            ["make check"]
+        );
+
+        // An escalated run resumes on the model it had reached, not the first one
+        let escalated = RunManifest {
+            model: Some("claude-haiku-4-5".into()),
+            model_chain: vec!["claude-haiku-4-5".into(), "claude-opus-4-5".into()],
+            escalation_level: Some(1),
+            ..manifest
+        };
+        let run = RunConfig::for_resume(&escalated, &config).unwrap();
+        assert_eq!(run.model.as_deref(), Some("claude-opus-4-5"));
+        assert_eq!(run.price.unwrap().input, 5.0);
+        assert_eq!(run.model_chain.len(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::outcome::{EXIT_CANCELLED, EXIT_CONFIG, EXIT_FAILURE, EXIT_TOOL_FAILED};
 use std::path::PathBuf;
 use thiserror::Error;
 
@@ -19,11 +20,183 @@ pub enum RalphError {
     #[error("User cancelled operation")]
     UserCancelled,
 
-    #[error("Either --dir (-d) or --task (-t) must be provided")]
+    #[error("Failed to read confirmation from stdin")]
+    ConfirmFailed(#[source] std::io::Error),
+
+    #[error("Stdin is not a terminal; pass --yes (-y) to run without confirmation")]
+    NotInteractive,
+
+    #[error("Task already exists: {path} (pick another name or remove it first)")]
+    TaskExists { path: PathBuf },
+
+    #[error("Invalid task name '{name}': use a single directory name such as 'add-login'")]
+    InvalidTaskName { name: String },
+
+    #[error("Unknown PRD template '{name}' (available: {available})")]
+    UnknownTemplate { name: String, available: String },
+
+    #[error("Failed to create task file: {path}")]
+    TaskCreate {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to read PRD file: {path}")]
+    PrdRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid front matter in {path}: {message}")]
+    InvalidFrontMatter { path: PathBuf, message: String },
+
+    #[error("Task '{task}' depends on {message}")]
+    TaskDependency { task: String, message: String },
+
+    #[error("Task dependencies form a cycle: {tasks}")]
+    DependencyCycle { tasks: String },
+
+    #[error("--parallel needs a git repository with at least one commit")]
+    NotAGitRepository,
+
+    #[error("Task '{task}' failed: {message}")]
+    TaskFailed { task: String, message: String },
+
+    #[error("Either --dir (-d), --task (-t) or --all must be provided")]
     NoInputProvided,
 
-    #[error("The '--variant' flag is only supported for opencode tool, not for '{tool}'")]
+    #[error("The '--variant' flag is not supported by the '{tool}' tool")]
     InvalidToolOption { tool: String },
+
+    #[error(
+        "Unknown tool '{name}': define it with `command` under [tools.{name}] in a config file"
+    )]
+    UnknownTool { name: String },
+
+    #[error("Invalid prompt template {path}: {message}")]
+    InvalidPrompt { path: PathBuf, message: String },
+
+    #[error("Invalid value for `{key}`: {message}")]
+    InvalidSetting { key: String, message: String },
+
+    #[error("No {key} configured: pass {flag} or set `{key}` in a config file")]
+    MissingSetting {
+        flag: &'static str,
+        key: &'static str,
+    },
+
+    #[error("Failed to read config file: {path}")]
+    ConfigRead {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to write run log: {path}")]
+    RunLog {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Failed to run verification command: {command}")]
+    VerifyFailed {
+        command: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{command} failed: {message}")]
+    Git { command: String, message: String },
+
+    #[error("Invalid run manifest {path}: {source}")]
+    InvalidManifest {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("No interrupted run found to resume")]
+    NothingToResume,
+
+    #[error("Invalid config file {path}: {source}")]
+    ConfigParse {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+}
+
+impl RalphError {
+    /// Process exit code for this error (see the table in README.md)
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RalphError::ToolFailed { .. } => EXIT_TOOL_FAILED,
+            RalphError::UserCancelled => EXIT_CANCELLED,
+            RalphError::ConfirmFailed(_)
+            | RalphError::RunLog { .. }
+            | RalphError::TaskCreate { .. }
+            | RalphError::TaskFailed { .. }
+            | RalphError::InvalidManifest { .. }
+            | RalphError::Git { .. }
+            | RalphError::VerifyFailed { .. } => EXIT_FAILURE,
+            RalphError::TaskNotFound { .. }
+            | RalphError::NoPrdFile { .. }
+            | RalphError::TaskExists { .. }
+            | RalphError::InvalidTaskName { .. }
+            | RalphError::UnknownTemplate { .. }
+            | RalphError::PrdRead { .. }
+            | RalphError::InvalidFrontMatter { .. }
+            | RalphError::TaskDependency { .. }
+            | RalphError::DependencyCycle { .. }
+            | RalphError::NotAGitRepository
+            | RalphError::NotInteractive
+            | RalphError::NothingToResume
+            | RalphError::NoInputProvided
+            | RalphError::InvalidToolOption { .. }
+            | RalphError::UnknownTool { .. }
+            | RalphError::InvalidSetting { .. }
+            | RalphError::InvalidPrompt { .. }
+            | RalphError::MissingSetting { .. }
+            | RalphError::ConfigRead { .. }
+            | RalphError::ConfigParse { .. } => EXIT_CONFIG,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, RalphError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_tool_failed() {
+        let err = RalphError::ToolFailed {
+            tool: "claude".into(),
+            source: std::io::Error::from(std::io::ErrorKind::NotFound),
+        };
+        assert_eq!(err.exit_code(), EXIT_TOOL_FAILED);
+    }
+
+    #[test]
+    fn test_exit_code_user_cancelled() {
+        assert_eq!(RalphError::UserCancelled.exit_code(), EXIT_CANCELLED);
+    }
+
+    #[test]
+    fn test_exit_code_config_errors() {
+        assert_eq!(RalphError::NoInputProvided.exit_code(), EXIT_CONFIG);
+        assert_eq!(RalphError::NotInteractive.exit_code(), EXIT_CONFIG);
+        assert_eq!(
+            RalphError::MissingSetting {
+                flag: "--tool",
+                key: "tool"
+            }
+            .exit_code(),
+            EXIT_CONFIG
+        );
+    }
+}
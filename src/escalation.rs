@@ -0,0 +1,124 @@
+use crate::config::ModelStep;
+
+/// Position in a model escalation chain: start with the cheapest model, move
+/// up after repeated failures or a stall, drop back once a PRD item is done
+pub struct Escalation {
+    chain: Vec<ModelStep>,
+    level: usize,
+    /// Failed iterations in a row that trigger escalation
+    after: u32,
+    failures: u32,
+}
+
+impl Escalation {
+    /// Start at `level` in the chain: 0 for a new run, or where a resumed run left off
+    pub fn new(chain: &[ModelStep], after: u32, level: usize) -> Self {
+        Self {
+            chain: chain.to_vec(),
+            level: level.min(chain.len().saturating_sub(1)),
+            after,
+            failures: 0,
+        }
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Whether a stronger model is left to escalate to
+    pub fn can_escalate(&self) -> bool {
+        self.level + 1 < self.chain.len()
+    }
+
+    /// Record a failed iteration; returns the next model once enough failures piled up
+    pub fn failed(&mut self) -> Option<&ModelStep> {
+        self.failures += 1;
+        if self.failures >= self.after {
+            self.escalate()
+        } else {
+            None
+        }
+    }
+
+    pub fn succeeded(&mut self) {
+        self.failures = 0;
+    }
+
+    /// Move to the next model, if any
+    pub fn escalate(&mut self) -> Option<&ModelStep> {
+        if !self.can_escalate() {
+            return None;
+        }
+        self.level += 1;
+        self.failures = 0;
+        self.chain.get(self.level)
+    }
+
+    /// A PRD item was completed: go back to the cheapest model for the next one
+    pub fn item_done(&mut self) -> Option<&ModelStep> {
+        self.failures = 0;
+        if self.level == 0 {
+            return None;
+        }
+        self.level = 0;
+        self.chain.first()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> Vec<ModelStep> {
+        ["haiku", "sonnet", "opus"]
+            .iter()
+            .map(|m| ModelStep {
+                model: m.to_string(),
+                price: None,
+            })
+            .collect()
+    }
+
+    fn model(step: Option<&ModelStep>) -> Option<&str> {
+        step.map(|s| s.model.as_str())
+    }
+
+    #[test]
+    fn test_escalates_after_repeated_failures() {
+        let mut escalation = Escalation::new(&chain(), 2, 0);
+        assert_eq!(model(escalation.failed()), None);
+        escalation.succeeded();
+        assert_eq!(model(escalation.failed()), None);
+        assert_eq!(model(escalation.failed()), Some("sonnet"));
+        assert_eq!(model(escalation.failed()), None);
+        assert_eq!(model(escalation.failed()), Some("opus"));
+        assert!(!escalation.can_escalate());
+        assert_eq!(model(escalation.failed()), None);
+        assert_eq!(model(escalation.failed()), None);
+    }
+
+    #[test]
+    fn test_item_done_drops_back_to_first_model() {
+        let mut escalation = Escalation::new(&chain(), 1, 0);
+        assert_eq!(model(escalation.item_done()), None);
+        assert_eq!(model(escalation.escalate()), Some("sonnet"));
+        assert_eq!(model(escalation.item_done()), Some("haiku"));
+    }
+
+    #[test]
+    fn test_resumes_at_recorded_level() {
+        let mut escalation = Escalation::new(&chain(), 1, 1);
+        assert_eq!(escalation.level(), 1);
+        assert_eq!(model(escalation.failed()), Some("opus"));
+        assert_eq!(Escalation::new(&chain(), 1, 7).level(), 2);
+        assert_eq!(Escalation::new(&[], 1, 1).level(), 0);
+    }
+
+    #[test]
+    fn test_empty_chain_never_escalates() {
+        let mut escalation = Escalation::new(&[], 1, 0);
+        assert!(!escalation.can_escalate());
+        assert_eq!(model(escalation.failed()), None);
+        assert_eq!(model(escalation.item_done()), None);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::error::{RalphError, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+/// `git status` entries with the size and modification time of each listed
+/// file, enough to notice edits without writing anything to the object store
+type WorktreeState = Vec<(String, Option<(u64, SystemTime)>)>;
+
+/// Working tree state recorded before an iteration so it can be restored
+pub struct Checkpoint {
+    repo: PathBuf,
+    pub head: String,
+    /// Commit holding uncommitted tracked changes (`git stash create`), if any
+    stash: Option<String>,
+    untracked: HashSet<PathBuf>,
+    /// State of the whole working tree, for spotting iterations that changed nothing
+    state: Option<WorktreeState>,
+}
+
+impl Checkpoint {
+    /// Record the current state, or `None` when `repo` is not inside a git work tree
+    pub fn create(repo: &Path) -> Result<Option<Self>> {
+        let Some(head) = head(repo) else {
+            return Ok(None);
+        };
+        // Work from the top so a run started in a subdirectory covers the whole repo
+        let repo = PathBuf::from(git(repo, &["rev-parse", "--show-toplevel"])?);
+        let stash = git(&repo, &["stash", "create"])?;
+        Ok(Some(Self {
+            head,
+            stash: (!stash.is_empty()).then_some(stash),
+            untracked: untracked_files(&repo)?,
+            state: worktree_state(&repo).ok(),
+            repo,
+        }))
+    }
+
+    /// Whether HEAD moved since the checkpoint
+    pub fn has_new_commits(&self) -> bool {
+        head(&self.repo).is_some_and(|head| head != self.head)
+    }
+
+    /// Whether any tracked or untracked (non-ignored) file differs from the checkpoint
+    pub fn has_file_changes(&self) -> bool {
+        self.state.is_some() && worktree_state(&self.repo).ok() != self.state
+    }
+
+    /// Restore HEAD, tracked changes and untracked files to the checkpoint
+    pub fn rollback(&self) -> Result<()> {
+        git(&self.repo, &["reset", "--hard", &self.head])?;
+        if let Some(ref stash) = self.stash {
+            git(&self.repo, &["stash", "apply", stash])?;
+        }
+        for path in untracked_files(&self.repo)?.difference(&self.untracked) {
+            let path = self.repo.join(path);
+            fs::remove_file(&path).map_err(|e| RalphError::Git {
+                command: format!("remove {}", path.display()),
+                message: e.to_string(),
+            })?;
+            // Directories the iteration created are left empty; remove them too
+            for dir in path.ancestors().skip(1) {
+                if dir == self.repo || fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Current HEAD commit, or `None` outside a git repository
+pub fn head(repo: &Path) -> Option<String> {
+    git(repo, &["rev-parse", "HEAD"]).ok()
+}
+
+pub fn short(rev: &str) -> &str {
+    &rev[..rev.len().min(7)]
+}
+
+/// Shared `.git` directory, the same for the main tree and every worktree
+pub fn common_dir(repo: &Path) -> Result<PathBuf> {
+    Ok(repo.join(git(repo, &["rev-parse", "--git-common-dir"])?))
+}
+
+/// Path of `repo` relative to the top of its work tree (`""` at the top)
+pub fn prefix(repo: &Path) -> Result<PathBuf> {
+    Ok(PathBuf::from(git(repo, &["rev-parse", "--show-prefix"])?))
+}
+
+/// Check out `branch` in a new worktree at `path`, creating the branch from
+/// `base` unless it already exists
+pub fn add_worktree(repo: &Path, path: &Path, branch: &str, base: &str) -> Result<()> {
+    let path = path.to_string_lossy();
+    if git(
+        repo,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{}", branch),
+        ],
+    )
+    .is_ok()
+    {
+        git(repo, &["worktree", "add", "--quiet", &path, branch])?;
+    } else {
+        git(
+            repo,
+            &["worktree", "add", "--quiet", "-b", branch, &path, base],
+        )?;
+    }
+    Ok(())
+}
+
+/// Commits on `branch` that HEAD does not have
+pub fn commits_ahead(repo: &Path, branch: &str) -> Option<usize> {
+    git(repo, &["rev-list", "--count", &format!("HEAD..{}", branch)])
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Whether merging `branch` into HEAD would succeed without conflicts, or
+/// `None` when git cannot tell (e.g. before git 2.38)
+pub fn merges_cleanly(repo: &Path, branch: &str) -> Option<bool> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["merge-tree", "--write-tree", "HEAD", branch])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    match status.code() {
+        Some(0) => Some(true),
+        Some(1) => Some(false),
+        _ => None,
+    }
+}
+
+fn untracked_files(repo: &Path) -> Result<HashSet<PathBuf>> {
+    let output = git(repo, &["ls-files", "--others", "--exclude-standard", "-z"])?;
+    Ok(output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn worktree_state(repo: &Path) -> Result<WorktreeState> {
+    let output = git_output(
+        repo,
+        &[
+            "--no-optional-locks",
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--no-renames",
+        ],
+    )?;
+    Ok(output
+        .split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let file = entry
+                .get(3..)
+                .and_then(|path| repo.join(path).metadata().ok());
+            let stamp = file.and_then(|meta| Some((meta.len(), meta.modified().ok()?)));
+            (entry.to_string(), stamp)
+        })
+        .collect())
+}
+
+/// Run a git command in `repo` and return its trimmed stdout
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    Ok(git_output(repo, args)?.trim().to_string())
+}
+
+/// Stdout of a git command, untrimmed for formats where leading spaces matter
+fn git_output(repo: &Path, args: &[&str]) -> Result<String> {
+    let git_error = |message: String| RalphError::Git {
+        command: format!("git {}", args.join(" ")),
+        message,
+    };
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()
+        .map_err(|e| git_error(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(git_error(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{tempdir, TempDir};
+
+    fn repo() -> TempDir {
+        let temp = tempdir().unwrap();
+        for args in [
+            &["init", "-q"][..],
+            &["config", "user.email", "ralph@example.com"],
+            &["config", "user.name", "ralph"],
+            &["config", "commit.gpgsign", "false"],
+        ] {
+            git(temp.path(), args).unwrap();
+        }
+        fs::write(temp.path().join("tracked.txt"), "one\n").unwrap();
+        commit(temp.path(), "initial");
+        temp
+    }
+
+    fn commit(repo: &Path, message: &str) {
+        git(repo, &["add", "-A"]).unwrap();
+        git(repo, &["commit", "-q", "-m", message]).unwrap();
+    }
+
+    #[test]
+    fn test_worktree_branch_merge_check() {
+        let repo = repo();
+        let worktree = repo.path().join(".git/ralph-worktrees/login");
+        let base = head(repo.path()).unwrap();
+        add_worktree(repo.path(), &worktree, "ralph/login", &base).unwrap();
+        assert_eq!(commits_ahead(repo.path(), "ralph/login"), Some(0));
+
+        fs::write(worktree.join("tracked.txt"), "login\n").unwrap();
+        commit(&worktree, "login");
+        assert_eq!(commits_ahead(repo.path(), "ralph/login"), Some(1));
+        assert_eq!(merges_cleanly(repo.path(), "ralph/login"), Some(true));
+
+        fs::write(repo.path().join("tracked.txt"), "main\n").unwrap();
+        commit(repo.path(), "main");
+        assert_eq!(merges_cleanly(repo.path(), "ralph/login"), Some(false));
+
+        // An existing branch is checked out again rather than recreated
+        git(
+            repo.path(),
+            &["worktree", "remove", &worktree.to_string_lossy()],
+        )
+        .unwrap();
+        add_worktree(repo.path(), &worktree, "ralph/login", &base).unwrap();
+        assert_eq!(
+            fs::read_to_string(worktree.join("tracked.txt")).unwrap(),
+            "login\n"
+        );
+    }
+
+    #[test]
+    fn test_checkpoint_outside_repo() {
+        let temp = tempdir().unwrap();
+        assert!(Checkpoint::create(temp.path()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_has_new_commits() {
+        let repo = repo();
+        let checkpoint = Checkpoint::create(repo.path()).unwrap().unwrap();
+        assert!(!checkpoint.has_new_commits());
+
+        fs::write(repo.path().join("tracked.txt"), "two\n").unwrap();
+        commit(repo.path(), "second");
+        assert!(checkpoint.has_new_commits());
+    }
+
+    #[test]
+    fn test_has_file_changes() {
+        let repo = repo();
+        let checkpoint = Checkpoint::create(repo.path()).unwrap().unwrap();
+        assert!(!checkpoint.has_file_changes());
+        let objects = git(repo.path(), &["count-objects"]).unwrap();
+
+        fs::write(repo.path().join("untracked.txt"), "new\n").unwrap();
+        assert!(checkpoint.has_file_changes());
+        fs::remove_file(repo.path().join("untracked.txt")).unwrap();
+        assert!(!checkpoint.has_file_changes());
+
+        fs::write(repo.path().join("tracked.txt"), "two\n").unwrap();
+        assert!(checkpoint.has_file_changes());
+        // Neither the real index nor the object store is touched
+        assert_eq!(
+            git(repo.path(), &["diff", "--cached", "--name-only"]).unwrap(),
+            ""
+        );
+        assert_eq!(git(repo.path(), &["count-objects"]).unwrap(), objects);
+    }
+
+    #[test]
+    fn test_has_file_changes_to_a_dirty_file() {
+        let repo = repo();
+        fs::write(repo.path().join("tracked.txt"), "dirty\n").unwrap();
+        let checkpoint = Checkpoint::create(repo.path()).unwrap().unwrap();
+        assert!(!checkpoint.has_file_changes());
+
+        fs::write(repo.path().join("tracked.txt"), "dirtier\n").unwrap();
+        assert!(checkpoint.has_file_changes());
+    }
+
+    #[test]
+    fn test_rollback_restores_commits_and_files() {
+        let repo = repo();
+        let checkpoint = Checkpoint::create(repo.path()).unwrap().unwrap();
+
+        fs::write(repo.path().join("tracked.txt"), "two\n").unwrap();
+        fs::write(repo.path().join("new.txt"), "new\n").unwrap();
+        commit(repo.path(), "bad");
+        fs::write(repo.path().join("stray.txt"), "stray\n").unwrap();
+
+        checkpoint.rollback().unwrap();
+
+        assert_eq!(head(repo.path()).unwrap(), checkpoint.head);
+        assert_eq!(
+            fs::read_to_string(repo.path().join("tracked.txt")).unwrap(),
+            "one\n"
+        );
+        assert!(!repo.path().join("new.txt").exists());
+        assert!(!repo.path().join("stray.txt").exists());
+    }
+
+    #[test]
+    fn test_rollback_from_subdirectory_removes_new_directories() {
+        let repo = repo();
+        let sub = repo.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        let checkpoint = Checkpoint::create(&sub).unwrap().unwrap();
+
+        fs::create_dir_all(repo.path().join("gen/deep")).unwrap();
+        fs::write(repo.path().join("gen/deep/out.txt"), "out\n").unwrap();
+        fs::write(repo.path().join("top.txt"), "top\n").unwrap();
+        assert!(checkpoint.has_file_changes());
+        checkpoint.rollback().unwrap();
+
+        assert!(!repo.path().join("gen").exists());
+        assert!(!repo.path().join("top.txt").exists());
+        assert!(sub.is_dir());
+    }
+
+    #[test]
+    fn test_rollback_keeps_preexisting_changes() {
+        let repo = repo();
+        fs::write(repo.path().join("tracked.txt"), "dirty\n").unwrap();
+        fs::write(repo.path().join("notes.txt"), "mine\n").unwrap();
+        let checkpoint = Checkpoint::create(repo.path()).unwrap().unwrap();
+
+        fs::write(repo.path().join("tracked.txt"), "broken\n").unwrap();
+        checkpoint.rollback().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(repo.path().join("tracked.txt")).unwrap(),
+            "dirty\n"
+        );
+        assert!(repo.path().join("notes.txt").exists());
+    }
+}
@@ -1,18 +1,50 @@
+mod budget;
 mod cli;
+mod commands;
+mod config;
 mod error;
+mod escalation;
+mod git;
+mod manifest;
 mod models;
+mod outcome;
+mod parallel;
+mod prd;
+mod prompt;
+mod queue;
+mod retry;
+mod rules;
 mod runner;
+mod signals;
+mod stall;
+mod status;
 mod task;
 mod tools;
+mod verify;
 
 use clap::Parser;
-use cli::Cli;
+use cli::{Cli, Command};
+use std::process::ExitCode;
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    if let Err(e) = runner::run(cli) {
-        eprintln!("Error: {e}");
-        std::process::exit(1);
+    let result = match cli.command {
+        Some(Command::Run(args)) => runner::run(args).map(|outcome| outcome.exit_code()),
+        Some(Command::Init(ref args)) => commands::init::run(args).map(|()| 0),
+        Some(Command::List(ref args)) => commands::status::list(args).map(|()| 0),
+        Some(Command::Status(ref args)) => commands::status::status(args).map(|()| 0),
+        Some(Command::Models(ref args)) => commands::models::run(args).map(|()| 0),
+        Some(Command::Resume(ref args)) => runner::resume(args).map(|outcome| outcome.exit_code()),
+        Some(Command::Doctor) => commands::doctor::run(),
+        None => runner::run(cli.run).map(|outcome| outcome.exit_code()),
+    };
+
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::from(e.exit_code())
+        }
     }
 }
@@ -0,0 +1,591 @@
+use crate::cli::ToolChoice;
+use crate::config::RunConfig;
+use crate::error::{RalphError, Result};
+use crate::outcome::RunOutcome;
+use crate::prd::PrdProgress;
+use crate::stall::StallAction;
+use crate::task::{TaskContext, TASKS_ROOT};
+use crate::tools::{ToolOutput, Usage};
+use crate::verify::CheckRecord;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory (inside the task dir) holding one subdirectory per run
+pub const RUNS_DIR: &str = "runs";
+pub const MANIFEST_FILE: &str = "manifest.json";
+const PROMPT_FILE: &str = "prompt.md";
+
+/// Index of a run, rewritten after every iteration so it survives crashes.
+/// A manifest without an `outcome` (killed run) or with an interrupted outcome
+/// marks a run that can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub task_dir: PathBuf,
+    pub prd_file: PathBuf,
+    pub progress_file: PathBuf,
+    pub tool: ToolChoice,
+    pub model: Option<String>,
+    /// Escalation chain when `--model` listed several models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_chain: Vec<String>,
+    /// Position in `model_chain` of the model in use, updated on every switch
+    /// so `ralph resume` continues on it (absent until the first switch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_level: Option<usize>,
+    pub variant: Option<String>,
+    pub iterations_planned: u32,
+    /// How the run was configured, so `ralph resume` continues it the same way
+    /// (absent in manifests written before settings were recorded)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<RunSettings>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub outcome: Option<RunOutcome>,
+    pub iterations: Vec<IterationRecord>,
+}
+
+/// Resolved run settings that flags or config may have changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSettings {
+    pub rollback: bool,
+    pub select_item: bool,
+    pub verify: Vec<String>,
+    pub iteration_timeout_secs: Option<u64>,
+    pub max_attempts: u32,
+    pub stall_after: u32,
+    pub on_stall: StallAction,
+    pub escalate_after: u32,
+    pub max_cost: Option<f64>,
+    pub max_tokens: Option<u64>,
+}
+
+impl RunSettings {
+    pub fn of(run: &RunConfig) -> Self {
+        Self {
+            rollback: run.rollback,
+            select_item: run.select_item,
+            verify: run.verify.clone(),
+            iteration_timeout_secs: run.iteration_timeout.map(|t| t.as_secs()),
+            max_attempts: run.retry.max_attempts,
+            stall_after: run.stall.after,
+            on_stall: run.stall.action,
+            escalate_after: run.escalate_after,
+            max_cost: run.max_cost,
+            max_tokens: run.max_tokens,
+        }
+    }
+}
+
+/// One tool invocation; file paths are relative to the run directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationRecord {
+    pub number: u32,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub exit_code: Option<i32>,
+    pub prompt: PathBuf,
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+    #[serde(flatten)]
+    pub details: IterationDetails,
+}
+
+/// What the runner concluded about an iteration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IterationDetails {
+    pub completed: bool,
+    /// The tool was killed for exceeding the iteration timeout
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// The tool was stopped by a second Ctrl-C
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interrupted: bool,
+    /// Tool invocations made, including retries (0 in manifests predating retries)
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitRecord>,
+    /// Tokens used and cost (reported by the tool or estimated from the price table)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Checked/total PRD task list items before and after the iteration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prd_before: Option<PrdProgress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prd_after: Option<PrdProgress>,
+    /// PRD item ralph selected for the iteration (`--select-item`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<ItemRecord>,
+    /// Model the iteration ran with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// PRD items the iteration checked off
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checked_items: Vec<String>,
+    /// Why the run counted as stalled after this iteration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stall: Option<String>,
+    /// Verification commands run after a completion claim
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<CheckRecord>,
+}
+
+/// A selected PRD item and whether the iteration checked it off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemRecord {
+    pub text: String,
+    pub line: usize,
+    pub checked: bool,
+}
+
+/// Repository state around an iteration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitRecord {
+    pub head_before: String,
+    pub head_after: Option<String>,
+    pub committed: bool,
+    pub rolled_back: bool,
+}
+
+/// Writes transcripts and the manifest for a run under `<task>/runs/<timestamp>/`
+pub struct RunRecorder {
+    dir: PathBuf,
+    manifest: RunManifest,
+    /// Contents of the run-level prompt file
+    prompt: String,
+}
+
+impl RunRecorder {
+    pub fn create(ctx: &TaskContext, run: &RunConfig, prompt: &str) -> Result<Self> {
+        let runs_dir = ctx.dir.join(RUNS_DIR);
+        fs::create_dir_all(&runs_dir).map_err(|e| io_error(&runs_dir, e))?;
+
+        // Keep transcripts out of the agent's commits
+        let gitignore = runs_dir.join(".gitignore");
+        if !gitignore.exists() {
+            fs::write(&gitignore, "*\n").map_err(|e| io_error(&gitignore, e))?;
+        }
+
+        let started_at = Utc::now();
+        let dir = unique_dir(&runs_dir, &started_at.format("%Y%m%d-%H%M%S").to_string());
+        fs::create_dir(&dir).map_err(|e| io_error(&dir, e))?;
+
+        let prompt_path = dir.join(PROMPT_FILE);
+        fs::write(&prompt_path, prompt).map_err(|e| io_error(&prompt_path, e))?;
+
+        let recorder = Self {
+            dir,
+            prompt: prompt.to_string(),
+            manifest: RunManifest {
+                task_dir: ctx.dir.clone(),
+                prd_file: ctx.prd_file.clone(),
+                progress_file: ctx.progress_file.clone(),
+                tool: run.tool.clone(),
+                model: run.model.clone(),
+                model_chain: run.model_chain.iter().map(|s| s.model.clone()).collect(),
+                escalation_level: None,
+                variant: run.variant.clone(),
+                iterations_planned: run.iterations,
+                settings: Some(RunSettings::of(run)),
+                started_at,
+                finished_at: None,
+                outcome: None,
+                iterations: Vec::new(),
+            },
+        };
+        recorder.write_manifest()?;
+        Ok(recorder)
+    }
+
+    /// Reopen an existing run directory to append further iterations
+    pub fn open(dir: &Path) -> Result<Self> {
+        let prompt_path = dir.join(PROMPT_FILE);
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            manifest: read_manifest(dir)?,
+            prompt: fs::read_to_string(&prompt_path).map_err(|e| io_error(&prompt_path, e))?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Usage of each recorded iteration that reported it
+    pub fn recorded_usage(&self) -> impl Iterator<Item = &Usage> {
+        self.manifest
+            .iterations
+            .iter()
+            .filter_map(|i| i.details.usage.as_ref())
+    }
+
+    /// PRD items checked off so far, with the model that did it
+    pub fn checked_items(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.manifest.iterations.iter().flat_map(|i| {
+            i.details
+                .checked_items
+                .iter()
+                .map(|text| (text.as_str(), i.details.model.as_deref()))
+        })
+    }
+
+    /// Stalls detected so far, as (iteration, reason)
+    pub fn stalls(&self) -> impl Iterator<Item = (u32, &str)> {
+        self.manifest
+            .iterations
+            .iter()
+            .filter_map(|i| Some((i.number, i.details.stall.as_deref()?)))
+    }
+
+    /// Position in the model chain the run had reached (0 before any switch)
+    pub fn escalation_level(&self) -> usize {
+        self.manifest.escalation_level.unwrap_or(0)
+    }
+
+    /// Remember a switch along the model chain
+    pub fn record_escalation(&mut self, level: usize) -> Result<()> {
+        self.manifest.escalation_level = Some(level);
+        self.write_manifest()
+    }
+
+    /// Number of the last recorded iteration (0 if none)
+    pub fn last_iteration(&self) -> u32 {
+        self.manifest.iterations.last().map_or(0, |i| i.number)
+    }
+
+    /// Save an iteration's output and append it to the manifest.
+    /// Prompts that differ from the run-level prompt get their own file.
+    pub fn record_iteration(
+        &mut self,
+        number: u32,
+        started_at: DateTime<Utc>,
+        prompt: &str,
+        output: &ToolOutput,
+        details: IterationDetails,
+    ) -> Result<()> {
+        let ended_at = Utc::now();
+        let prompt_file = if prompt == self.prompt {
+            PathBuf::from(PROMPT_FILE)
+        } else {
+            let name = PathBuf::from(format!("iteration-{:03}.prompt.md", number));
+            self.write_file(&name, prompt)?;
+            name
+        };
+        let stdout = PathBuf::from(format!("iteration-{:03}.stdout.log", number));
+        let stderr = PathBuf::from(format!("iteration-{:03}.stderr.log", number));
+        self.write_file(&stdout, &output.stdout)?;
+        self.write_file(&stderr, &output.stderr)?;
+
+        self.manifest.iterations.push(IterationRecord {
+            number,
+            started_at,
+            ended_at,
+            duration_ms: (ended_at - started_at).num_milliseconds().max(0) as u64,
+            exit_code: output.status.code(),
+            prompt: prompt_file,
+            stdout,
+            stderr,
+            details,
+        });
+        self.write_manifest()
+    }
+
+    pub fn finish(&mut self, outcome: &RunOutcome) -> Result<()> {
+        self.manifest.finished_at = Some(Utc::now());
+        self.manifest.outcome = Some(outcome.clone());
+        self.write_manifest()
+    }
+
+    fn write_file(&self, name: &Path, content: &str) -> Result<()> {
+        let path = self.dir.join(name);
+        fs::write(&path, content).map_err(|e| io_error(&path, e))
+    }
+
+    fn write_manifest(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.manifest)
+            .expect("manifest serialization cannot fail");
+        self.write_file(Path::new(MANIFEST_FILE), &json)
+    }
+}
+
+pub fn read_manifest(run_dir: &Path) -> Result<RunManifest> {
+    let path = run_dir.join(MANIFEST_FILE);
+    let json = fs::read_to_string(&path).map_err(|e| io_error(&path, e))?;
+    serde_json::from_str(&json).map_err(|e| RalphError::InvalidManifest {
+        path: path.clone(),
+        source: e,
+    })
+}
+
+/// All readable runs of a task, oldest first
+pub fn load_runs(task_dir: &Path) -> Vec<(PathBuf, RunManifest)> {
+    let mut runs: Vec<_> = fs::read_dir(task_dir.join(RUNS_DIR))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .filter_map(|dir| read_manifest(&dir).ok().map(|m| (dir, m)))
+        .collect();
+    runs.sort_by_key(|(_, manifest)| manifest.started_at);
+    runs
+}
+
+/// Most recent unfinished run, in `task_dir` or across all tasks under `.ai/tasks`
+pub fn find_resumable(task_dir: Option<&Path>) -> Option<(PathBuf, RunManifest)> {
+    let task_dirs: Vec<PathBuf> = match task_dir {
+        Some(dir) => vec![dir.to_path_buf()],
+        None => TaskContext::list(Path::new(TASKS_ROOT)),
+    };
+
+    task_dirs
+        .iter()
+        .flat_map(|dir| load_runs(dir))
+        .filter(|(_, manifest)| {
+            manifest
+                .outcome
+                .as_ref()
+                .is_none_or(RunOutcome::is_resumable)
+        })
+        .max_by_key(|(_, manifest)| manifest.started_at)
+}
+
+/// Pick `<base>`, or `<base>-2`, `<base>-3`... if runs started in the same second
+fn unique_dir(parent: &Path, base: &str) -> PathBuf {
+    let mut dir = parent.join(base);
+    let mut n = 2;
+    while dir.exists() {
+        dir = parent.join(format!("{}-{}", base, n));
+        n += 1;
+    }
+    dir
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> RalphError {
+    RalphError::RunLog {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+    use tempfile::tempdir;
+
+    fn context(dir: &Path) -> TaskContext {
+        TaskContext {
+            dir: dir.to_path_buf(),
+            prd_file: dir.join("PRD.md"),
+            progress_file: dir.join("PROGRESS.md"),
+        }
+    }
+
+    fn run_config() -> RunConfig {
+        RunConfig {
+            tool: ToolChoice::Claude,
+            model: Some("claude-opus-4-5".into()),
+            variant: None,
+            iterations: 3,
+            rollback: false,
+            select_item: false,
+            verify: Vec::new(),
+            iteration_timeout: None,
+            retry: Default::default(),
+            stall: Default::default(),
+            max_cost: None,
+            max_tokens: None,
+            price: None,
+            model_chain: Vec::new(),
+            escalate_after: 2,
+            command: None,
+        }
+    }
+
+    fn output(code: i32) -> ToolOutput {
+        ToolOutput {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: "out\n".into(),
+            stderr: "err\n".into(),
+            timed_out: false,
+            interrupted: false,
+            events: Vec::new(),
+        }
+    }
+
+    fn completed() -> IterationDetails {
+        IterationDetails {
+            completed: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_create_writes_prompt_and_manifest() {
+        let temp = tempdir().unwrap();
+        let recorder = RunRecorder::create(&context(temp.path()), &run_config(), "do it").unwrap();
+
+        assert!(recorder.dir().starts_with(temp.path().join(RUNS_DIR)));
+        assert_eq!(
+            fs::read_to_string(recorder.dir().join(PROMPT_FILE)).unwrap(),
+            "do it"
+        );
+        assert!(temp.path().join(RUNS_DIR).join(".gitignore").exists());
+
+        let manifest = read_manifest(recorder.dir()).unwrap();
+        assert!(matches!(manifest.tool, ToolChoice::Claude));
+        assert_eq!(manifest.iterations_planned, 3);
+        assert_eq!(manifest.settings, Some(RunSettings::of(&run_config())));
+        assert!(manifest.outcome.is_none());
+        assert!(manifest.iterations.is_empty());
+    }
+
+    #[test]
+    fn test_record_iteration_and_finish() {
+        let temp = tempdir().unwrap();
+        let mut recorder =
+            RunRecorder::create(&context(temp.path()), &run_config(), "prompt").unwrap();
+        recorder
+            .record_iteration(1, Utc::now(), "prompt", &output(0), completed())
+            .unwrap();
+        recorder
+            .finish(&RunOutcome::Complete { iterations: 1 })
+            .unwrap();
+
+        let manifest = read_manifest(recorder.dir()).unwrap();
+        assert_eq!(manifest.iterations.len(), 1);
+        let iteration = &manifest.iterations[0];
+        assert_eq!(iteration.exit_code, Some(0));
+        assert!(iteration.details.completed);
+        assert_eq!(
+            fs::read_to_string(recorder.dir().join(&iteration.stdout)).unwrap(),
+            "out\n"
+        );
+        assert_eq!(
+            fs::read_to_string(recorder.dir().join(&iteration.stderr)).unwrap(),
+            "err\n"
+        );
+        assert_eq!(
+            manifest.outcome,
+            Some(RunOutcome::Complete { iterations: 1 })
+        );
+        assert!(manifest.finished_at.is_some());
+    }
+
+    #[test]
+    fn test_record_iteration_writes_changed_prompt() {
+        let temp = tempdir().unwrap();
+        let mut recorder =
+            RunRecorder::create(&context(temp.path()), &run_config(), "prompt").unwrap();
+
+        recorder
+            .record_iteration(1, Utc::now(), "prompt", &output(0), Default::default())
+            .unwrap();
+        recorder
+            .record_iteration(
+                2,
+                Utc::now(),
+                "prompt\nNOTE",
+                &output(0),
+                Default::default(),
+            )
+            .unwrap();
+
+        let manifest = read_manifest(recorder.dir()).unwrap();
+        assert_eq!(manifest.iterations[0].prompt, PathBuf::from(PROMPT_FILE));
+        let second = &manifest.iterations[1].prompt;
+        assert_eq!(second, &PathBuf::from("iteration-002.prompt.md"));
+        assert_eq!(
+            fs::read_to_string(recorder.dir().join(second)).unwrap(),
+            "prompt\nNOTE"
+        );
+    }
+
+    #[test]
+    fn test_open_appends_to_existing_run() {
+        let temp = tempdir().unwrap();
+        let dir = RunRecorder::create(&context(temp.path()), &run_config(), "prompt")
+            .unwrap()
+            .dir()
+            .to_path_buf();
+
+        let mut recorder = RunRecorder::open(&dir).unwrap();
+        recorder
+            .record_iteration(
+                1,
+                Utc::now(),
+                "prompt",
+                &output(0),
+                IterationDetails::default(),
+            )
+            .unwrap();
+
+        assert_eq!(read_manifest(&dir).unwrap().iterations.len(), 1);
+    }
+
+    #[test]
+    fn test_escalation_level_survives_resume() {
+        let temp = tempdir().unwrap();
+        let mut recorder =
+            RunRecorder::create(&context(temp.path()), &run_config(), "prompt").unwrap();
+        assert_eq!(recorder.escalation_level(), 0);
+        recorder.record_escalation(2).unwrap();
+
+        let reopened = RunRecorder::open(recorder.dir()).unwrap();
+        assert_eq!(reopened.escalation_level(), 2);
+    }
+
+    #[test]
+    fn test_find_resumable_skips_finished_runs() {
+        let temp = tempdir().unwrap();
+        let ctx = context(temp.path());
+        let unfinished = RunRecorder::create(&ctx, &run_config(), "prompt")
+            .unwrap()
+            .dir()
+            .to_path_buf();
+        let mut finished = RunRecorder::create(&ctx, &run_config(), "prompt").unwrap();
+        finished
+            .finish(&RunOutcome::Exhausted { iterations: 3 })
+            .unwrap();
+
+        let (dir, manifest) = find_resumable(Some(temp.path())).unwrap();
+        assert_eq!(dir, unfinished);
+        assert!(manifest.outcome.is_none());
+    }
+
+    #[test]
+    fn test_find_resumable_includes_interrupted_runs() {
+        let temp = tempdir().unwrap();
+        let mut recorder =
+            RunRecorder::create(&context(temp.path()), &run_config(), "prompt").unwrap();
+        recorder
+            .finish(&RunOutcome::Interrupted { iterations: 1 })
+            .unwrap();
+
+        assert!(find_resumable(Some(temp.path())).is_some());
+    }
+
+    #[test]
+    fn test_find_resumable_none_when_all_finished() {
+        let temp = tempdir().unwrap();
+        let mut recorder =
+            RunRecorder::create(&context(temp.path()), &run_config(), "prompt").unwrap();
+        recorder
+            .finish(&RunOutcome::Complete { iterations: 1 })
+            .unwrap();
+
+        assert!(find_resumable(Some(temp.path())).is_none());
+    }
+
+    #[test]
+    fn test_unique_dir_suffixes_collisions() {
+        let temp = tempdir().unwrap();
+        fs::create_dir(temp.path().join("run")).unwrap();
+        assert_eq!(unique_dir(temp.path(), "run"), temp.path().join("run-2"));
+    }
+}
@@ -1,6 +1,10 @@
 use crate::cli::ToolChoice;
+use crate::config::{Config, ConfigAlias};
+use crate::tools::Usage;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 /// Model alias mappings for OpenCode
 static OPENCODE_ALIASES: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
@@ -93,18 +97,106 @@ static CODEX_ALIASES: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
     m
 });
 
-/// Resolve a model alias to its full name for the given tool
-pub fn resolve_model(tool: &ToolChoice, model: &str) -> String {
+/// Model alias mappings for Gemini CLI
+static GEMINI_ALIASES: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert("pro", "gemini-2.5-pro");
+    m.insert("flash", "gemini-2.5-flash");
+    m.insert("flash-lite", "gemini-2.5-flash-lite");
+    m
+});
+
+/// Model alias mappings for Aider (litellm provider/model names)
+static AIDER_ALIASES: Lazy<HashMap<&str, &str>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    m.insert("opus", "anthropic/claude-opus-4-5");
+    m.insert("sonnet", "anthropic/claude-sonnet-4-5");
+    m.insert("haiku", "anthropic/claude-haiku-4-5");
+    m.insert("gpt-5", "openai/gpt-5");
+    m.insert("gemini", "gemini/gemini-2.5-pro");
+    m
+});
+
+/// Built-in alias table for a tool (if any)
+fn builtin_aliases(tool: &ToolChoice) -> Option<&'static HashMap<&'static str, &'static str>> {
     match tool {
-        ToolChoice::OpenCode => OPENCODE_ALIASES
-            .get(model)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| model.to_string()),
-        ToolChoice::Codex => CODEX_ALIASES
-            .get(model)
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| model.to_string()),
-        ToolChoice::Claude => model.to_string(),
+        ToolChoice::OpenCode => Some(&OPENCODE_ALIASES),
+        ToolChoice::Codex => Some(&CODEX_ALIASES),
+        ToolChoice::Gemini => Some(&GEMINI_ALIASES),
+        ToolChoice::Aider => Some(&AIDER_ALIASES),
+        ToolChoice::Claude | ToolChoice::Command(_) => None,
+    }
+}
+
+/// Resolve a built-in model alias to its full name for the given tool
+pub fn resolve_model(tool: &ToolChoice, model: &str) -> String {
+    builtin_aliases(tool)
+        .and_then(|aliases| aliases.get(model))
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| model.to_string())
+}
+
+/// Where an alias definition comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum AliasSource {
+    BuiltIn,
+    Config(PathBuf),
+}
+
+/// A single effective alias entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasEntry {
+    pub alias: String,
+    pub model: String,
+    pub source: AliasSource,
+}
+
+/// Effective aliases for one tool: config-defined aliases merged over the built-ins
+pub struct AliasTable {
+    tool: ToolChoice,
+    overrides: HashMap<String, ConfigAlias>,
+}
+
+impl AliasTable {
+    pub fn new(tool: &ToolChoice, config: &Config) -> Self {
+        Self {
+            tool: tool.clone(),
+            overrides: config.aliases(tool).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Resolve an alias; config aliases may point at a built-in alias
+    pub fn resolve(&self, model: &str) -> String {
+        match self.overrides.get(model) {
+            Some(alias) => resolve_model(&self.tool, &alias.model),
+            None => resolve_model(&self.tool, model),
+        }
+    }
+
+    /// All effective entries sorted by alias name
+    pub fn entries(&self) -> Vec<AliasEntry> {
+        let mut entries = BTreeMap::new();
+        for (alias, model) in builtin_aliases(&self.tool).into_iter().flatten() {
+            entries.insert(
+                alias.to_string(),
+                AliasEntry {
+                    alias: alias.to_string(),
+                    model: model.to_string(),
+                    source: AliasSource::BuiltIn,
+                },
+            );
+        }
+        for (alias, config_alias) in &self.overrides {
+            entries.insert(
+                alias.clone(),
+                AliasEntry {
+                    alias: alias.clone(),
+                    model: self.resolve(alias),
+                    source: AliasSource::Config(config_alias.source.clone()),
+                },
+            );
+        }
+        entries.into_values().collect()
     }
 }
 
@@ -116,6 +208,91 @@ pub fn default_model(tool: &ToolChoice) -> Option<String> {
     }
 }
 
+/// USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl ModelPrice {
+    const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read,
+            cache_write,
+        }
+    }
+
+    pub fn cost(&self, usage: &Usage) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_read_tokens as f64 * self.cache_read
+            + usage.cache_write_tokens as f64 * self.cache_write)
+            / 1_000_000.0
+    }
+}
+
+/// Published list prices, keyed by model name without provider prefix or
+/// snapshot date
+static MODEL_PRICES: Lazy<HashMap<&str, ModelPrice>> = Lazy::new(|| {
+    let mut m = HashMap::new();
+    // Anthropic
+    m.insert("claude-opus-4-5", ModelPrice::new(5.0, 25.0, 0.5, 6.25));
+    m.insert("claude-opus-4-1", ModelPrice::new(15.0, 75.0, 1.5, 18.75));
+    m.insert("claude-opus-4", ModelPrice::new(15.0, 75.0, 1.5, 18.75));
+    m.insert("claude-sonnet-4-5", ModelPrice::new(3.0, 15.0, 0.3, 3.75));
+    m.insert("claude-sonnet-4", ModelPrice::new(3.0, 15.0, 0.3, 3.75));
+    m.insert("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75));
+    m.insert("claude-haiku-4-5", ModelPrice::new(1.0, 5.0, 0.1, 1.25));
+    m.insert("claude-3-5-haiku", ModelPrice::new(0.8, 4.0, 0.08, 1.0));
+    // OpenAI
+    m.insert("gpt-5", ModelPrice::new(1.25, 10.0, 0.125, 0.0));
+    m.insert("gpt-5.1-codex", ModelPrice::new(1.25, 10.0, 0.125, 0.0));
+    m.insert("gpt-5.1-codex-max", ModelPrice::new(1.25, 10.0, 0.125, 0.0));
+    m.insert("gpt-5.1-codex-mini", ModelPrice::new(0.25, 2.0, 0.025, 0.0));
+    m.insert("gpt-5.2", ModelPrice::new(1.75, 14.0, 0.175, 0.0));
+    m.insert("gpt-5.2-codex", ModelPrice::new(1.75, 14.0, 0.175, 0.0));
+    // Google
+    m.insert("gemini-2.5-pro", ModelPrice::new(1.25, 10.0, 0.125, 0.0));
+    m.insert("gemini-2.5-flash", ModelPrice::new(0.3, 2.5, 0.03, 0.0));
+    m.insert(
+        "gemini-2.5-flash-lite",
+        ModelPrice::new(0.1, 0.4, 0.01, 0.0),
+    );
+    m
+});
+
+/// Price of a model: config overrides (`[prices]`) first, then the built-in
+/// table. The provider prefix and a `-YYYYMMDD` or `-latest` suffix are
+/// ignored; any other unknown name has no price rather than a guessed one.
+pub fn model_price(model: &str, config: &Config) -> Option<ModelPrice> {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    let base = undated(name);
+    [model, name, base]
+        .iter()
+        .find_map(|key| config.prices.get(*key))
+        .or_else(|| MODEL_PRICES.get(base))
+        .copied()
+}
+
+/// `name` without a trailing snapshot date (`-20250929`) or `-latest`
+fn undated(name: &str) -> &str {
+    if let Some(base) = name.strip_suffix("-latest") {
+        return base;
+    }
+    match name.rsplit_once('-') {
+        Some((base, date)) if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,6 +377,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_resolve_model_gemini_alias() {
+        assert_eq!(
+            resolve_model(&ToolChoice::Gemini, "flash"),
+            "gemini-2.5-flash"
+        );
+    }
+
+    #[test]
+    fn test_resolve_model_aider_alias() {
+        assert_eq!(
+            resolve_model(&ToolChoice::Aider, "sonnet"),
+            "anthropic/claude-sonnet-4-5"
+        );
+        assert_eq!(
+            resolve_model(&ToolChoice::Aider, "deepseek/deepseek-chat"),
+            "deepseek/deepseek-chat"
+        );
+    }
+
     #[test]
     fn test_resolve_model_claude_passthrough() {
         // Claude tool doesn't have aliases - everything passes through
@@ -209,6 +406,54 @@ mod tests {
         );
     }
 
+    fn config_with_aliases(content: &str) -> Config {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_alias_table_config_overrides_builtin() {
+        let config =
+            config_with_aliases("[aliases.opencode]\nopus = \"anthropic/claude-opus-4-1\"\n");
+        let table = AliasTable::new(&ToolChoice::OpenCode, &config);
+
+        assert_eq!(table.resolve("opus"), "anthropic/claude-opus-4-1");
+        assert_eq!(table.resolve("sonnet"), "anthropic/claude-sonnet-4-5");
+    }
+
+    #[test]
+    fn test_alias_table_config_alias_chains_to_builtin() {
+        let config = config_with_aliases("[aliases.codex]\ndefault = \"codex-mini\"\n");
+        let table = AliasTable::new(&ToolChoice::Codex, &config);
+
+        assert_eq!(table.resolve("default"), "gpt-5.1-codex-mini");
+    }
+
+    #[test]
+    fn test_alias_table_claude_config_aliases() {
+        let config = config_with_aliases("[aliases.claude]\nbig = \"claude-opus-4-5\"\n");
+        let table = AliasTable::new(&ToolChoice::Claude, &config);
+
+        assert_eq!(table.resolve("big"), "claude-opus-4-5");
+        assert_eq!(
+            table.entries(),
+            vec![AliasEntry {
+                alias: "big".into(),
+                model: "claude-opus-4-5".into(),
+                source: AliasSource::Config(PathBuf::new()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_alias_table_entries_mark_builtins() {
+        let table = AliasTable::new(&ToolChoice::Codex, &Config::default());
+        let entries = table.entries();
+
+        assert_eq!(entries.len(), CODEX_ALIASES.len());
+        assert!(entries.iter().all(|e| e.source == AliasSource::BuiltIn));
+        assert_eq!(entries[0].alias, "codex");
+    }
+
     #[test]
     fn test_default_model_opencode() {
         assert_eq!(
@@ -226,4 +471,53 @@ mod tests {
     fn test_default_model_codex() {
         assert_eq!(default_model(&ToolChoice::Codex), None);
     }
+
+    #[test]
+    fn test_model_price_strips_provider_and_date() {
+        let config = Config::default();
+        let price = model_price("anthropic/claude-sonnet-4-5-20250929", &config).unwrap();
+        assert_eq!(price.input, 3.0);
+        assert_eq!(
+            model_price("gpt-5.1-codex-mini", &config).unwrap().input,
+            0.25
+        );
+        assert_eq!(model_price("mystery-model", &config), None);
+        let latest = model_price("claude-haiku-4-5-latest", &config).unwrap();
+        assert_eq!(latest.input, 1.0);
+    }
+
+    #[test]
+    fn test_model_price_unknown_variants_are_not_guessed() {
+        let config = Config::default();
+        for model in [
+            "gpt-5-mini",
+            "gpt-5-nano",
+            "openai/gpt-5-mini",
+            "claude-opus-4-6",
+        ] {
+            assert_eq!(model_price(model, &config), None, "{}", model);
+        }
+        assert_eq!(model_price("gpt-5", &config).unwrap().input, 1.25);
+    }
+
+    #[test]
+    fn test_model_price_config_override() {
+        let mut config = Config::default();
+        config
+            .prices
+            .insert("mystery-model".into(), ModelPrice::new(2.0, 4.0, 0.0, 0.0));
+        assert_eq!(model_price("mystery-model", &config).unwrap().output, 4.0);
+    }
+
+    #[test]
+    fn test_model_price_cost() {
+        let usage = Usage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_read_tokens: 2_000_000,
+            ..Default::default()
+        };
+        let cost = ModelPrice::new(3.0, 15.0, 0.3, 3.75).cost(&usage);
+        assert!((cost - 5.1).abs() < 1e-9);
+    }
 }
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+/// Exit code: the PRD was completed
+pub const EXIT_COMPLETE: u8 = 0;
+/// Exit code: unexpected failure not covered by a more specific code
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code: all iterations ran without the PRD being completed
+pub const EXIT_EXHAUSTED: u8 = 3;
+/// Exit code: the AI tool could not be run
+pub const EXIT_TOOL_FAILED: u8 = 4;
+/// Exit code: the user declined the confirmation prompt
+pub const EXIT_CANCELLED: u8 = 5;
+/// Exit code: invalid flags, config files or task layout
+pub const EXIT_CONFIG: u8 = 6;
+/// Exit code: stopped because the cost or token budget would be exceeded
+pub const EXIT_BUDGET: u8 = 7;
+/// Exit code: iterations stopped making progress and the stall policy ended the run
+pub const EXIT_STALLED: u8 = 8;
+/// Exit code: stopped by SIGINT/SIGTERM (128 + SIGINT, as shells report it)
+pub const EXIT_INTERRUPTED: u8 = 130;
+
+/// How a run loop ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum RunOutcome {
+    /// The tool reported the PRD as complete
+    Complete { iterations: u32 },
+    /// The iteration budget ran out first
+    Exhausted { iterations: u32 },
+    /// The user stopped the run with Ctrl-C or SIGTERM
+    Interrupted { iterations: u32 },
+    /// Another iteration would have gone over `--max-cost` or `--max-tokens`
+    OverBudget { iterations: u32 },
+    /// Iterations stopped making progress (see `StallPolicy`)
+    Stalled { iterations: u32, reason: String },
+}
+
+impl RunOutcome {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RunOutcome::Complete { .. } => EXIT_COMPLETE,
+            RunOutcome::Exhausted { .. } => EXIT_EXHAUSTED,
+            RunOutcome::Interrupted { .. } => EXIT_INTERRUPTED,
+            RunOutcome::OverBudget { .. } => EXIT_BUDGET,
+            RunOutcome::Stalled { .. } => EXIT_STALLED,
+        }
+    }
+
+    /// Iterations the run went through
+    pub fn iterations(&self) -> u32 {
+        match *self {
+            RunOutcome::Complete { iterations }
+            | RunOutcome::Exhausted { iterations }
+            | RunOutcome::Interrupted { iterations }
+            | RunOutcome::OverBudget { iterations }
+            | RunOutcome::Stalled { iterations, .. } => iterations,
+        }
+    }
+
+    /// Short name for listings
+    pub fn label(&self) -> &'static str {
+        match self {
+            RunOutcome::Complete { .. } => "complete",
+            RunOutcome::Exhausted { .. } => "exhausted",
+            RunOutcome::Interrupted { .. } => "interrupted",
+            RunOutcome::OverBudget { .. } => "over budget",
+            RunOutcome::Stalled { .. } => "stalled",
+        }
+    }
+
+    /// Whether `ralph resume` may continue a run that ended this way
+    pub fn is_resumable(&self) -> bool {
+        matches!(
+            self,
+            RunOutcome::Interrupted { .. }
+                | RunOutcome::OverBudget { .. }
+                | RunOutcome::Stalled { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_complete() {
+        assert_eq!(RunOutcome::Complete { iterations: 2 }.exit_code(), 0);
+    }
+
+    #[test]
+    fn test_exit_code_exhausted() {
+        assert_eq!(RunOutcome::Exhausted { iterations: 5 }.exit_code(), 3);
+    }
+
+    #[test]
+    fn test_exit_code_interrupted() {
+        assert_eq!(RunOutcome::Interrupted { iterations: 1 }.exit_code(), 130);
+    }
+
+    #[test]
+    fn test_exit_code_over_budget() {
+        assert_eq!(RunOutcome::OverBudget { iterations: 2 }.exit_code(), 7);
+    }
+
+    #[test]
+    fn test_exit_code_stalled() {
+        let outcome = RunOutcome::Stalled {
+            iterations: 3,
+            reason: "idle".into(),
+        };
+        assert_eq!(outcome.exit_code(), 8);
+        assert!(outcome.is_resumable());
+    }
+
+    #[test]
+    fn test_iterations_and_label() {
+        let outcome = RunOutcome::OverBudget { iterations: 4 };
+        assert_eq!(outcome.iterations(), 4);
+        assert_eq!(outcome.label(), "over budget");
+    }
+
+    #[test]
+    fn test_resumable_outcomes() {
+        assert!(RunOutcome::Interrupted { iterations: 1 }.is_resumable());
+        assert!(RunOutcome::OverBudget { iterations: 1 }.is_resumable());
+        assert!(!RunOutcome::Complete { iterations: 1 }.is_resumable());
+        assert!(!RunOutcome::Exhausted { iterations: 1 }.is_resumable());
+    }
+}
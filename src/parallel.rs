@@ -0,0 +1,527 @@
+use crate::cli::RunArgs;
+use crate::config::LOCAL_CONFIG_FILE;
+use crate::error::{RalphError, Result};
+use crate::git;
+use crate::manifest::load_runs;
+use crate::outcome::RunOutcome;
+use crate::queue::QueuedTask;
+use crate::signals::{self, Interrupt};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Worktrees live under the shared git directory, out of sight of `git status`
+const WORKTREES_DIR: &str = "ralph-worktrees";
+
+/// How often to check on running tasks
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Branch a task's worktree is checked out on
+fn branch_name(task: &str) -> String {
+    format!("ralph/{}", task)
+}
+
+/// How a queued task ended
+enum Finished {
+    Outcome(RunOutcome),
+    /// The worktree could not be set up, or ralph failed outside the run loop
+    Failed(String),
+    /// A queued dependency did not complete
+    Skipped,
+}
+
+/// A task whose ralph process is running in its worktree
+struct Running {
+    index: usize,
+    child: Child,
+    /// Directory the child runs in: the worktree plus the current directory's prefix
+    cwd: PathBuf,
+    output: Vec<JoinHandle<()>>,
+}
+
+/// Run the queue `jobs` tasks at a time, each as a child `ralph run` in its
+/// own worktree, with output lines prefixed by the task name. A task with a
+/// queued dependency starts once it completes, from the dependency's branch.
+pub fn run(cli: &RunArgs, jobs: usize, queue: &[QueuedTask]) -> Result<RunOutcome> {
+    let repo = Path::new(".");
+    let base = git::head(repo).ok_or(RalphError::NotAGitRepository)?;
+    let worktrees = git::common_dir(repo)?.join(WORKTREES_DIR);
+    let prefix = git::prefix(repo)?;
+    let dependencies = queued_dependencies(queue)?;
+
+    let width = queue.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    let mut results: Vec<Option<Finished>> = queue.iter().map(|_| None).collect();
+    let mut running: Vec<Running> = Vec::new();
+    let mut forwarded = 0;
+    signals::install();
+
+    loop {
+        let interrupt = signals::check();
+        // Pass each Ctrl-C on, so children stop after their iteration or abort
+        let level = match interrupt {
+            Interrupt::None => 0,
+            Interrupt::StopAfterIteration => 1,
+            Interrupt::Abort => 2,
+        };
+        while forwarded < level {
+            for task in &running {
+                // SAFETY: kill(2) takes plain integers and touches no memory; the
+                // child has not been reaped yet, so its pid still refers to it
+                unsafe { libc::kill(task.child.id() as libc::pid_t, libc::SIGINT) };
+            }
+            forwarded += 1;
+        }
+
+        for (i, task) in queue.iter().enumerate() {
+            if running.len() >= jobs || interrupt != Interrupt::None {
+                break;
+            }
+            if results[i].is_some() || running.iter().any(|r| r.index == i) {
+                continue;
+            }
+            let start_from = match dependencies[i] {
+                None => base.clone(),
+                Some(dep) => match results[dep] {
+                    Some(Finished::Outcome(RunOutcome::Complete { .. })) => {
+                        branch_name(&queue[dep].name)
+                    }
+                    Some(_) => {
+                        results[i] = Some(Finished::Skipped);
+                        continue;
+                    }
+                    None => continue,
+                },
+            };
+
+            let label = format!("[{:<width$}] ", task.name);
+            let worktree = worktrees.join(&task.name);
+            match start(cli, task, &worktree, &prefix, &start_from, &label) {
+                Ok((child, cwd, output)) => {
+                    println!(
+                        "{}started on {} in {}",
+                        label,
+                        branch_name(&task.name),
+                        worktree.display()
+                    );
+                    running.push(Running {
+                        index: i,
+                        child,
+                        cwd,
+                        output,
+                    });
+                }
+                Err(RalphError::TaskFailed { message, .. }) => {
+                    results[i] = Some(Finished::Failed(message))
+                }
+                Err(e) => results[i] = Some(Finished::Failed(e.to_string())),
+            }
+        }
+
+        if running.is_empty() {
+            break;
+        }
+        thread::sleep(POLL_INTERVAL);
+
+        let mut still_running = Vec::new();
+        for mut task in running {
+            match task.child.try_wait() {
+                Ok(None) => still_running.push(task),
+                Ok(Some(status)) => {
+                    for handle in task.output.drain(..) {
+                        handle.join().ok();
+                    }
+                    let finished = finish(&queue[task.index], &task.cwd, status);
+                    results[task.index] = Some(finished);
+                }
+                Err(e) => results[task.index] = Some(Finished::Failed(e.to_string())),
+            }
+        }
+        running = still_running;
+    }
+
+    print_report(repo, queue, &results, &worktrees);
+    overall(queue, results)
+}
+
+/// Index of each task's dependency within the queue. Worktrees branch from
+/// one place, so a task can wait for at most one other queued task.
+fn queued_dependencies(queue: &[QueuedTask]) -> Result<Vec<Option<usize>>> {
+    queue
+        .iter()
+        .map(|task| {
+            let deps: Vec<(usize, &str)> = task
+                .front
+                .depends_on
+                .iter()
+                .filter_map(|dep| {
+                    let index = queue.iter().position(|t| &t.name == dep)?;
+                    Some((index, dep.as_str()))
+                })
+                .collect();
+            match deps.as_slice() {
+                [] => Ok(None),
+                [(index, _)] => Ok(Some(*index)),
+                _ => Err(RalphError::TaskDependency {
+                    task: task.name.clone(),
+                    message: format!(
+                        "several queued tasks ({}), which --parallel cannot combine; run them without it",
+                        deps.iter().map(|(_, d)| *d).collect::<Vec<_>>().join(", ")
+                    ),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Set up the worktree and start `ralph run` for `task` in it
+fn start(
+    cli: &RunArgs,
+    task: &QueuedTask,
+    worktree: &Path,
+    prefix: &Path,
+    start_from: &str,
+    label: &str,
+) -> Result<(Child, PathBuf, Vec<JoinHandle<()>>)> {
+    let failed = |message: String| RalphError::TaskFailed {
+        task: task.name.clone(),
+        message,
+    };
+
+    // A worktree left by an earlier parallel run is picked up where it was
+    if !worktree.exists() {
+        git::add_worktree(
+            Path::new("."),
+            worktree,
+            &branch_name(&task.name),
+            start_from,
+        )?;
+    }
+    let cwd = worktree.join(prefix);
+    let prd = relative_to_cwd(&task.ctx.prd_file)
+        .ok_or_else(|| failed("its PRD is outside the repository".into()))?;
+    if !cwd.join(&prd).is_file() {
+        return Err(failed(format!(
+            "{} is not in the worktree; commit the task first",
+            prd.display()
+        )));
+    }
+    // Untracked personal config would otherwise be missing in the worktree
+    let local_config = Path::new(LOCAL_CONFIG_FILE);
+    if local_config.is_file() && !cwd.join(local_config).exists() {
+        fs::copy(local_config, cwd.join(local_config)).map_err(|e| {
+            failed(format!(
+                "could not copy {} into its worktree: {}",
+                LOCAL_CONFIG_FILE, e
+            ))
+        })?;
+    }
+
+    let exe = env::current_exe().map_err(|e| failed(e.to_string()))?;
+    let mut child = Command::new(exe)
+        .args(child_args(cli, &prd))
+        .current_dir(&cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Own process group: Ctrl-C reaches ralph, which forwards it once
+        .process_group(0)
+        .spawn()
+        .map_err(|e| failed(format!("could not start ralph: {}", e)))?;
+
+    let output = vec![
+        forward(child.stdout.take(), label.to_string(), false),
+        forward(child.stderr.take(), label.to_string(), true),
+    ];
+    Ok((child, cwd, output))
+}
+
+/// A task path relative to the current directory, so it can be looked up in a worktree
+fn relative_to_cwd(path: &Path) -> Option<PathBuf> {
+    if path.is_relative() {
+        return Some(path.to_path_buf());
+    }
+    let cwd = env::current_dir().ok()?;
+    path.strip_prefix(cwd).ok().map(Path::to_path_buf)
+}
+
+/// Arguments for one task's `ralph run`: the task plus every run flag given on
+/// the command line. Config files are read again by the child in its worktree.
+fn child_args(cli: &RunArgs, prd: &Path) -> Vec<OsString> {
+    let mut args: Vec<OsString> = vec!["run".into(), "--task".into(), prd.into(), "--yes".into()];
+    let mut flag = |name: &str, value: Option<String>| {
+        if let Some(value) = value {
+            args.push(name.into());
+            args.push(value.into());
+        }
+    };
+    flag("--iterations", cli.iterations.map(|n| n.to_string()));
+    flag("--tool", cli.tool.as_ref().map(|t| t.to_string()));
+    flag("--model", cli.model.clone());
+    flag("--variant", cli.variant.clone());
+    flag(
+        "--escalate-after",
+        cli.escalate_after.map(|n| n.to_string()),
+    );
+    flag(
+        "--iteration-timeout",
+        cli.iteration_timeout.map(|t| format!("{}s", t.as_secs())),
+    );
+    flag("--max-attempts", cli.max_attempts.map(|n| n.to_string()));
+    flag("--stall-after", cli.stall_after.map(|n| n.to_string()));
+    flag("--on-stall", cli.on_stall.map(|a| a.to_string()));
+    flag("--max-cost", cli.max_cost.map(|c| c.to_string()));
+    flag("--max-tokens", cli.max_tokens.map(|n| n.to_string()));
+    for command in &cli.verify {
+        flag("--verify", Some(command.clone()));
+    }
+    if cli.rollback {
+        args.push("--rollback".into());
+    }
+    if cli.select_item {
+        args.push("--select-item".into());
+    }
+    args
+}
+
+/// Copy lines from a child's stdout or stderr to ours, prefixed with its task
+fn forward<R: Read + Send + 'static>(
+    stream: Option<R>,
+    label: String,
+    stderr: bool,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let Some(stream) = stream else {
+            return;
+        };
+        for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+            if stderr {
+                eprintln!("{}{}", label, line);
+            } else {
+                println!("{}{}", label, line);
+            }
+        }
+    })
+}
+
+/// The outcome the child recorded in its run manifest, if its exit code agrees
+fn finish(task: &QueuedTask, cwd: &Path, status: ExitStatus) -> Finished {
+    let Some(code) = status.code() else {
+        return Finished::Failed(format!(
+            "killed by signal {}",
+            status.signal().unwrap_or_default()
+        ));
+    };
+    // The task dir may be absolute; its runs are under the same path in the worktree
+    let Some(task_dir) = relative_to_cwd(&task.ctx.dir) else {
+        return Finished::Failed("its task dir is outside the repository".into());
+    };
+    let outcome = load_runs(&cwd.join(task_dir))
+        .pop()
+        .and_then(|(_, manifest)| manifest.outcome)
+        .filter(|outcome| i32::from(outcome.exit_code()) == code);
+    match outcome {
+        Some(outcome) => Finished::Outcome(outcome),
+        None => Finished::Failed(format!("exited with code {}", code)),
+    }
+}
+
+fn print_report(repo: &Path, queue: &[QueuedTask], results: &[Option<Finished>], worktrees: &Path) {
+    let rows: Vec<[String; 3]> = queue
+        .iter()
+        .zip(results)
+        .map(|(task, result)| {
+            let branch = branch_name(&task.name);
+            let (result, next) = match result {
+                Some(Finished::Outcome(outcome)) => (
+                    format!(
+                        "{} after {} iteration(s)",
+                        outcome.label(),
+                        outcome.iterations()
+                    ),
+                    match outcome {
+                        RunOutcome::Complete { .. } => merge_status(repo, &branch),
+                        _ => format!("unfinished on {}", branch),
+                    },
+                ),
+                Some(Finished::Failed(message)) => (format!("failed: {}", message), String::new()),
+                Some(Finished::Skipped) => (
+                    "skipped (depends on an unfinished task)".to_string(),
+                    String::new(),
+                ),
+                None => ("not run".to_string(), String::new()),
+            };
+            [task.name.clone(), result, next]
+        })
+        .collect();
+
+    let name_width = rows.iter().map(|r| r[0].len()).max().unwrap_or(0);
+    let result_width = rows.iter().map(|r| r[1].len()).max().unwrap_or(0);
+    println!("\nParallel summary:");
+    for [name, result, next] in &rows {
+        let line = format!(
+            "  {:<name_width$}  {:<result_width$}  {}",
+            name, result, next
+        );
+        println!("{}", line.trim_end());
+    }
+    println!(
+        "\nWorktrees are in {}; remove one with `git worktree remove <path>`.",
+        worktrees.display()
+    );
+}
+
+/// What is left to do with a completed task's branch
+fn merge_status(repo: &Path, branch: &str) -> String {
+    if git::commits_ahead(repo, branch) == Some(0) {
+        return format!("{} has no new commits", branch);
+    }
+    match git::merges_cleanly(repo, branch) {
+        Some(true) => format!("ready: git merge {}", branch),
+        Some(false) => format!("needs manual merge: {} conflicts with HEAD", branch),
+        None => format!("merge {} by hand", branch),
+    }
+}
+
+/// An interrupt wins, then the first failure, then the first task that did not
+/// complete; otherwise the queue completed
+fn overall(queue: &[QueuedTask], results: Vec<Option<Finished>>) -> Result<RunOutcome> {
+    let mut iterations = 0;
+    let mut failure = None;
+    let mut unfinished = None;
+    for (task, result) in queue.iter().zip(results) {
+        match result {
+            Some(Finished::Outcome(outcome @ RunOutcome::Interrupted { .. })) => {
+                return Ok(outcome)
+            }
+            Some(Finished::Outcome(RunOutcome::Complete { iterations: n })) => iterations += n,
+            Some(Finished::Outcome(outcome)) => {
+                unfinished.get_or_insert(outcome);
+            }
+            Some(Finished::Failed(message)) => {
+                failure.get_or_insert(RalphError::TaskFailed {
+                    task: task.name.clone(),
+                    message,
+                });
+            }
+            Some(Finished::Skipped) | None => {}
+        }
+    }
+    match (failure, unfinished) {
+        (Some(e), _) => Err(e),
+        (None, Some(outcome)) => Ok(outcome),
+        (None, None) => Ok(RunOutcome::Complete { iterations }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ToolChoice;
+    use crate::queue::FrontMatter;
+    use crate::stall::StallAction;
+
+    fn task(name: &str, depends_on: &[&str]) -> QueuedTask {
+        let dir = PathBuf::from(".ai/tasks").join(name);
+        QueuedTask {
+            name: name.to_string(),
+            ctx: crate::task::TaskContext {
+                prd_file: dir.join("PRD.md"),
+                progress_file: dir.join("PROGRESS.md"),
+                dir,
+            },
+            front: FrontMatter {
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                priority: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_child_args_forward_run_flags() {
+        let cli = RunArgs {
+            iterations: Some(5),
+            tool: Some(ToolChoice::Claude),
+            model: Some("haiku,opus".into()),
+            verify: vec!["cargo test".into()],
+            iteration_timeout: Some(Duration::from_secs(900)),
+            on_stall: Some(StallAction::Nudge),
+            select_item: true,
+            parallel: Some(3),
+            ..RunArgs::default()
+        };
+        let args = child_args(&cli, Path::new(".ai/tasks/login/PRD.md"));
+        let args: Vec<&str> = args.iter().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--task",
+                ".ai/tasks/login/PRD.md",
+                "--yes",
+                "--iterations",
+                "5",
+                "--tool",
+                "claude",
+                "--model",
+                "haiku,opus",
+                "--iteration-timeout",
+                "900s",
+                "--on-stall",
+                "nudge",
+                "--verify",
+                "cargo test",
+                "--select-item",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_queued_dependencies() {
+        let queue = vec![task("schema", &[]), task("api", &["schema", "done"])];
+        assert_eq!(queued_dependencies(&queue).unwrap(), [None, Some(0)]);
+
+        let queue = vec![task("a", &[]), task("b", &[]), task("c", &["a", "b"])];
+        match queued_dependencies(&queue) {
+            Err(RalphError::TaskDependency { task, message }) => {
+                assert_eq!(task, "c");
+                assert!(message.contains("a, b"));
+            }
+            _ => panic!("Expected TaskDependency error"),
+        }
+    }
+
+    #[test]
+    fn test_overall_outcome() {
+        let queue = vec![task("a", &[]), task("b", &[]), task("c", &[])];
+        let complete = || Some(Finished::Outcome(RunOutcome::Complete { iterations: 2 }));
+
+        let all_done = overall(&queue, vec![complete(), complete(), complete()]).unwrap();
+        assert_eq!(all_done, RunOutcome::Complete { iterations: 6 });
+
+        let results = vec![
+            complete(),
+            Some(Finished::Outcome(RunOutcome::Exhausted { iterations: 5 })),
+            Some(Finished::Skipped),
+        ];
+        assert_eq!(
+            overall(&queue, results).unwrap(),
+            RunOutcome::Exhausted { iterations: 5 }
+        );
+
+        let results = vec![
+            Some(Finished::Failed("exited with code 4".into())),
+            Some(Finished::Outcome(RunOutcome::Exhausted { iterations: 5 })),
+            None,
+        ];
+        assert!(matches!(
+            overall(&queue, results),
+            Err(RalphError::TaskFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,304 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// One markdown task list entry (`- [ ] ...` / `- [x] ...`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrdItem {
+    pub text: String,
+    pub checked: bool,
+    /// Nesting level, 0 for top-level items
+    pub depth: usize,
+    /// Closest heading above the item
+    pub section: Option<String>,
+    /// 1-based line number in the PRD
+    pub line: usize,
+}
+
+/// Priority tags such as `P0`, `[P1]` or `(p2)`; lower numbers go first
+static PRIORITY_TAG: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bP(\d)\b").unwrap());
+
+impl PrdItem {
+    /// Priority from the item's own tag, if it has one
+    pub fn priority(&self) -> Option<u32> {
+        PRIORITY_TAG
+            .captures(&self.text)
+            .and_then(|c| c[1].parse().ok())
+    }
+
+    /// Whether `other` is the same item after the agent edited the PRD.
+    /// Matched by text, since lines shift when the agent adds or removes some.
+    pub fn same_item(&self, other: &PrdItem) -> bool {
+        self.text == other.text
+    }
+}
+
+/// The item to work on next: among unchecked items without unchecked
+/// children, the one with the highest priority (tags are inherited from
+/// parent items; untagged items come last), then the first in the document
+pub fn next_item(items: &[PrdItem]) -> Option<&PrdItem> {
+    let mut best: Option<(u32, &PrdItem)> = None;
+    // Effective priority of the enclosing items, indexed by depth
+    let mut inherited: Vec<Option<u32>> = Vec::new();
+
+    for (index, item) in items.iter().enumerate() {
+        inherited.truncate(item.depth);
+        let priority = item
+            .priority()
+            .or_else(|| inherited.iter().rev().find_map(|p| *p));
+        inherited.resize(item.depth, None);
+        inherited.push(priority);
+
+        let has_open_children = items[index + 1..]
+            .iter()
+            .take_while(|child| child.depth > item.depth)
+            .any(|child| !child.checked);
+        if item.checked || has_open_children {
+            continue;
+        }
+
+        let rank = priority.unwrap_or(u32::MAX);
+        if best.is_none_or(|(best_rank, _)| rank < best_rank) {
+            best = Some((rank, item));
+        }
+    }
+
+    best.map(|(_, item)| item)
+}
+
+/// Items checked in `after` that were not checked in `before` (matched by text)
+pub fn newly_checked<'a>(before: &[PrdItem], after: &'a [PrdItem]) -> Vec<&'a PrdItem> {
+    after
+        .iter()
+        .filter(|item| item.checked)
+        .filter(|item| !before.iter().any(|b| b.checked && b.text == item.text))
+        .collect()
+}
+
+/// Checked vs. total task list items
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PrdProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl PrdProgress {
+    pub fn of(items: &[PrdItem]) -> Self {
+        Self {
+            done: items.iter().filter(|i| i.checked).count(),
+            total: items.len(),
+        }
+    }
+
+    /// Progress over `items`; `None` when the PRD has no task list
+    pub fn count(items: &[PrdItem]) -> Option<Self> {
+        Some(Self::of(items)).filter(|p| p.total > 0)
+    }
+
+    /// Read the PRD and count its items; `None` if it cannot be read or has no task list
+    pub fn read(path: &Path) -> Option<Self> {
+        Self::count(&read_items(path))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.done == self.total
+    }
+}
+
+impl fmt::Display for PrdProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.done, self.total)
+    }
+}
+
+/// Task list items of a PRD file; none if it cannot be read
+pub fn read_items(path: &Path) -> Vec<PrdItem> {
+    fs::read_to_string(path)
+        .map(|content| parse(&content))
+        .unwrap_or_default()
+}
+
+/// Parse every task list item in a markdown document, skipping fenced code blocks
+pub fn parse(content: &str) -> Vec<PrdItem> {
+    let mut items = Vec::new();
+    let mut section = None;
+    let mut fence: Option<&str> = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            continue;
+        }
+        if let Some(marker) = ["```", "~~~"].into_iter().find(|m| trimmed.starts_with(m)) {
+            fence = Some(marker);
+            continue;
+        }
+
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#');
+            if heading.is_empty() || heading.starts_with(' ') {
+                section = Some(heading.trim().to_string());
+                continue;
+            }
+        }
+
+        if let Some((checked, text)) = parse_checkbox(trimmed) {
+            items.push(PrdItem {
+                text: text.to_string(),
+                checked,
+                depth: indent_width(line) / 2,
+                section: section.clone(),
+                line: index + 1,
+            });
+        }
+    }
+
+    items
+}
+
+/// `- [x] text`, `* [ ] text` or `1. [ ] text`
+fn parse_checkbox(line: &str) -> Option<(bool, &str)> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let digits = line.find(|c: char| !c.is_ascii_digit())?;
+            if digits == 0 {
+                return None;
+            }
+            line[digits..].strip_prefix(['.', ')'])?
+        }
+    };
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let text = &rest[3..];
+    if !(text.is_empty() || text.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((checked, text.trim()))
+}
+
+/// Leading whitespace width, counting tabs as four spaces
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRD: &str = "\
+# Login
+
+## Backend
+- [x] Add users table
+- [ ] Password hashing
+  - [X] Pick algorithm
+  - [ ] Wire into signup
+
+## Frontend
+1. [ ] Login form
+* [ ]not a checkbox
+- [] not a checkbox either
+
+```md
+- [ ] inside a code block
+```
+";
+
+    #[test]
+    fn test_parse_items() {
+        let items = parse(PRD);
+        let texts: Vec<&str> = items.iter().map(|i| i.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "Add users table",
+                "Password hashing",
+                "Pick algorithm",
+                "Wire into signup",
+                "Login form"
+            ]
+        );
+        assert_eq!(items[2].depth, 1);
+        assert!(items[2].checked);
+        assert_eq!(items[3].section.as_deref(), Some("Backend"));
+        assert_eq!(items[4].section.as_deref(), Some("Frontend"));
+        assert_eq!(items[4].line, 10);
+    }
+
+    #[test]
+    fn test_progress() {
+        let progress = PrdProgress::of(&parse(PRD));
+        assert_eq!(progress, PrdProgress { done: 2, total: 5 });
+        assert_eq!(progress.to_string(), "2/5");
+        assert!(!progress.is_complete());
+
+        let done = PrdProgress::of(&parse("- [x] a\n- [x] b\n"));
+        assert!(done.is_complete());
+        assert!(!PrdProgress::of(&[]).is_complete());
+        assert_eq!(PrdProgress::count(&[]), None);
+    }
+
+    #[test]
+    fn test_newly_checked() {
+        let before = parse("- [x] a\n- [ ] b\n- [ ] c\n");
+        let after = parse("- [x] a\n- [x] b\n- [ ] c\n- [x] d\n");
+        let texts: Vec<&str> = newly_checked(&before, &after)
+            .iter()
+            .map(|i| i.text.as_str())
+            .collect();
+        assert_eq!(texts, ["b", "d"]);
+    }
+
+    #[test]
+    fn test_same_item_ignores_shifted_lines() {
+        let before = parse("- [ ] setup\n- [x] schema\n- [ ] login\n");
+        let selected = &before[2];
+        // A new item at the top moves the checked "schema" onto the selected line
+        let after = parse("- [x] notes\n- [ ] setup\n- [x] schema\n- [ ] login\n");
+        assert_eq!(after[2].line, selected.line);
+        assert!(!after.iter().any(|i| i.checked && selected.same_item(i)));
+
+        let after = parse("- [x] notes\n- [ ] setup\n- [x] schema\n- [x] login\n");
+        assert!(after.iter().any(|i| i.checked && selected.same_item(i)));
+    }
+
+    #[test]
+    fn test_next_item_in_document_order() {
+        let items = parse(PRD);
+        // "Password hashing" still has an open child, so the child goes first
+        assert_eq!(next_item(&items).unwrap().text, "Wire into signup");
+        assert!(next_item(&parse("- [x] a\n- [x] b\n")).is_none());
+        assert!(next_item(&[]).is_none());
+    }
+
+    #[test]
+    fn test_next_item_by_priority() {
+        let items = parse(
+            "- [ ] untagged\n\
+             - [ ] [P2] later\n\
+             - [ ] (P1) parent\n  \
+               - [x] done child\n  \
+               - [ ] open child\n\
+             - [ ] P1 sibling\n",
+        );
+        assert_eq!(items[1].priority(), Some(2));
+        assert_eq!(items[0].priority(), None);
+        // Inherits P1 from its parent and comes before the later P1 sibling
+        assert_eq!(next_item(&items).unwrap().text, "open child");
+    }
+}
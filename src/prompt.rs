@@ -0,0 +1,542 @@
+use crate::cli::ToolChoice;
+use crate::error::{RalphError, Result};
+use crate::prd::PrdItem;
+use crate::rules::RulesFile;
+use crate::task::TaskContext;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Per-task prompt template, looked up in the task directory
+pub const TASK_PROMPT_FILE: &str = "PROMPT.md";
+
+/// Repo-wide prompt template, relative to the current directory
+pub const REPO_PROMPT_FILE: &str = ".ralph/PROMPT.md";
+
+/// Used when neither the task nor the repo provides a template
+const DEFAULT_TEMPLATE: &str = "{{files}} \
+    {{#item}}1. Implement this PRD item (line {{item_line}}): {{item}} \
+    Check it off in the PRD when it is done. {{/item}}\
+    {{^item}}1. Find the highest-priority task and implement it. {{/item}}\
+    2. Run your tests and type checks. \
+    3. Update the PRD with what was done. \
+    4. Append your progress to PROGRESS.md. \
+    5. Commit your changes. \
+    ONLY WORK ON A SINGLE TASK. \
+    {{#rules}}YOU **MUST** OBEY RULES SPECIFIED IN {{rules}}. {{/rules}}\
+    If the PRD is complete, output {{completion_marker}}.";
+
+/// Variables a template may reference as `{{name}}`
+const VARIABLES: &[&str] = &[
+    "prd",
+    "progress",
+    "rules",
+    "files",
+    "prd_path",
+    "progress_path",
+    "rules_path",
+    "iteration",
+    "iterations",
+    "remaining",
+    "tool",
+    "task",
+    "completion_marker",
+    "item",
+    "item_line",
+];
+
+/// Prompt template with `{{variable}}` placeholders, `{{#variable}}...{{/variable}}`
+/// sections, which are left out when the variable is empty, and inverted
+/// `{{^variable}}...{{/variable}}` sections, kept only when it is empty
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    text: String,
+    /// File the template was read from (`None` for the built-in default)
+    source: Option<PathBuf>,
+}
+
+/// Values for one iteration's prompt
+pub struct PromptVars<'a> {
+    pub ctx: &'a TaskContext,
+    pub tool: &'a ToolChoice,
+    pub rules: &'a [RulesFile],
+    pub iteration: u32,
+    pub iterations: u32,
+    /// PRD item selected by ralph, if item selection is enabled
+    pub item: Option<&'a PrdItem>,
+    /// What the active tool recognizes as a completion claim
+    pub completion_marker: &'a str,
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self {
+            text: DEFAULT_TEMPLATE.to_string(),
+            source: None,
+        }
+    }
+}
+
+impl PromptTemplate {
+    /// Template for a task: its own `PROMPT.md`, then the repo template, then the default
+    pub fn load(ctx: &TaskContext) -> Result<Self> {
+        for path in [
+            ctx.dir.join(TASK_PROMPT_FILE),
+            PathBuf::from(REPO_PROMPT_FILE),
+        ] {
+            if path.is_file() {
+                return Self::from_file(&path);
+            }
+        }
+        Ok(Self::default())
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let invalid = |message: String| RalphError::InvalidPrompt {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        // Reject unknown variables up front rather than on the first iteration
+        substitute(&text, &|name| VARIABLES.contains(&name).then(String::new)).map_err(invalid)?;
+        Ok(Self {
+            text,
+            source: Some(path.to_path_buf()),
+        })
+    }
+
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    pub fn render(&self, vars: &PromptVars) -> Result<String> {
+        let prompt = substitute(&self.text, &|name| vars.get(name)).map_err(|message| {
+            RalphError::InvalidPrompt {
+                path: self
+                    .source
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from("(built-in)")),
+                message,
+            }
+        })?;
+        Ok(prompt.trim_end().to_string())
+    }
+}
+
+impl PromptVars<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        let ctx = self.ctx;
+        let value = match name {
+            "prd" => self.reference(&ctx.prd_file.display().to_string()),
+            "progress" => self.reference(&ctx.progress_file.display().to_string()),
+            "rules" => self.rules_references(),
+            "files" if attaches_files(self.tool) => {
+                let files = format!(
+                    "@{} @{}",
+                    ctx.progress_file.display(),
+                    ctx.prd_file.display()
+                );
+                [files, self.rules_references()]
+                    .join(" ")
+                    .trim_end()
+                    .to_string()
+            }
+            "files" => format!(
+                "PRD file: {}. PROGRESS file: {}.",
+                ctx.prd_file.display(),
+                ctx.progress_file.display()
+            ),
+            "prd_path" => ctx.prd_file.display().to_string(),
+            "progress_path" => ctx.progress_file.display().to_string(),
+            "rules_path" => self
+                .rules
+                .iter()
+                .map(|r| r.display.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            "iteration" => self.iteration.to_string(),
+            "iterations" => self.iterations.to_string(),
+            "remaining" => self.iterations.saturating_sub(self.iteration).to_string(),
+            "tool" => self.tool.to_string(),
+            "task" => ctx
+                .dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            "completion_marker" => self.completion_marker.to_string(),
+            "item" => self.item.map(|i| i.text.clone()).unwrap_or_default(),
+            "item_line" => self.item.map(|i| i.line.to_string()).unwrap_or_default(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn rules_references(&self) -> String {
+        let references: Vec<String> = self
+            .rules
+            .iter()
+            .map(|r| self.reference(&r.display))
+            .collect();
+        let separator = if attaches_files(self.tool) { " " } else { ", " };
+        references.join(separator)
+    }
+
+    /// A file reference in the tool's native style
+    fn reference(&self, path: &str) -> String {
+        if attaches_files(self.tool) {
+            format!("@{}", path)
+        } else {
+            path.to_string()
+        }
+    }
+}
+
+/// Whether the tool expands `@path` references into attached file contents
+fn attaches_files(tool: &ToolChoice) -> bool {
+    matches!(
+        tool,
+        ToolChoice::OpenCode | ToolChoice::Claude | ToolChoice::Gemini
+    )
+}
+
+/// Replace every `{{name}}` using `lookup`, expand `{{#name}}...{{/name}}`
+/// sections when `name` is non-empty and `{{^name}}...{{/name}}` when it is
+/// empty; unknown names and unclosed tags are errors, including inside
+/// sections that are left out
+fn substitute(
+    text: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> std::result::Result<String, String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| "unclosed '{{' in template".to_string())?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+
+        let section = match tag.chars().next() {
+            Some('#') => Some(false),
+            Some('^') => Some(true),
+            _ => None,
+        };
+        if let Some(inverted) = section {
+            let name = tag[1..].trim();
+            let close = format!("{{{{/{}}}}}", name);
+            let section_end = rest.find(&close).ok_or_else(|| {
+                format!("section '{{{{{}{}}}}}' is never closed", &tag[..1], name)
+            })?;
+            let value = lookup(name).ok_or_else(|| unknown_variable(name))?;
+            let body = substitute(&rest[..section_end], lookup)?;
+            if value.is_empty() == inverted {
+                output.push_str(&body);
+            }
+            rest = &rest[section_end + close.len()..];
+            continue;
+        }
+
+        let name = tag;
+        let value = lookup(name).ok_or_else(|| unknown_variable(name))?;
+        output.push_str(&value);
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn unknown_variable(name: &str) -> String {
+    format!(
+        "unknown variable '{{{{{}}}}}' (available: {})",
+        name,
+        VARIABLES.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::COMPLETION_MARKER;
+    use tempfile::tempdir;
+
+    fn ctx(dir: &Path) -> TaskContext {
+        TaskContext {
+            dir: dir.to_path_buf(),
+            prd_file: dir.join("PRD.md"),
+            progress_file: dir.join("PROGRESS.md"),
+        }
+    }
+
+    fn global_rules() -> Vec<RulesFile> {
+        vec![RulesFile {
+            display: "~/.agents/AGENTS.md".into(),
+            path: PathBuf::from("/home/user/.agents/AGENTS.md"),
+        }]
+    }
+
+    fn vars<'a>(
+        ctx: &'a TaskContext,
+        tool: &'a ToolChoice,
+        rules: &'a [RulesFile],
+    ) -> PromptVars<'a> {
+        PromptVars {
+            ctx,
+            tool,
+            rules,
+            iteration: 2,
+            iterations: 5,
+            item: None,
+            completion_marker: COMPLETION_MARKER,
+        }
+    }
+
+    fn default_prompt(tool: &ToolChoice) -> String {
+        let ctx = ctx(Path::new("/test/dir"));
+        PromptTemplate::default()
+            .render(&vars(&ctx, tool, &global_rules()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_default_prompt_opencode() {
+        let prompt = default_prompt(&ToolChoice::OpenCode);
+
+        assert!(prompt.contains("@/test/dir/PROGRESS.md"));
+        assert!(prompt.contains("@/test/dir/PRD.md"));
+        assert!(prompt.contains("@~/.agents/AGENTS.md"));
+        assert!(prompt.contains("highest-priority task"));
+        assert!(prompt.contains("<promise>COMPLETE</promise>"));
+    }
+
+    #[test]
+    fn test_default_prompt_claude() {
+        let prompt = default_prompt(&ToolChoice::Claude);
+
+        assert!(prompt.contains("@/test/dir/PROGRESS.md"));
+        assert!(prompt.contains("@/test/dir/PRD.md"));
+        assert!(prompt.contains("@~/.agents/AGENTS.md"));
+    }
+
+    #[test]
+    fn test_default_prompt_codex() {
+        let prompt = default_prompt(&ToolChoice::Codex);
+
+        assert!(prompt.contains("PRD file: /test/dir/PRD.md"));
+        assert!(prompt.contains("PROGRESS file: /test/dir/PROGRESS.md"));
+        assert!(prompt.contains("~/.agents/AGENTS.md"));
+        assert!(!prompt.contains("@~/.agents/AGENTS.md"));
+    }
+
+    #[test]
+    fn test_default_prompt_gemini() {
+        let prompt = default_prompt(&ToolChoice::Gemini);
+
+        assert!(prompt.contains("@/test/dir/PROGRESS.md"));
+        assert!(prompt.contains("@/test/dir/PRD.md"));
+        assert!(prompt.contains("@~/.agents/AGENTS.md"));
+    }
+
+    #[test]
+    fn test_default_prompt_aider() {
+        let prompt = default_prompt(&ToolChoice::Aider);
+
+        assert!(prompt.contains("PRD file: /test/dir/PRD.md"));
+        assert!(prompt.contains("PROGRESS file: /test/dir/PROGRESS.md"));
+        assert!(!prompt.contains("@"));
+    }
+
+    #[test]
+    fn test_completion_marker() {
+        assert_eq!(COMPLETION_MARKER, "<promise>COMPLETE</promise>");
+    }
+
+    #[test]
+    fn test_default_prompt_with_tool_completion_marker() {
+        let ctx = ctx(Path::new("/test/dir"));
+        let tool = ToolChoice::Command("wrap".into());
+        let prompt = PromptTemplate::default()
+            .render(&PromptVars {
+                completion_marker: "STATUS: COMPLETE",
+                ..vars(&ctx, &tool, &[])
+            })
+            .unwrap();
+
+        assert!(prompt.ends_with("If the PRD is complete, output STATUS: COMPLETE."));
+        assert!(!prompt.contains(COMPLETION_MARKER));
+    }
+
+    #[test]
+    fn test_default_prompt_matches_previous_hard_coded_prompt() {
+        assert_eq!(
+            default_prompt(&ToolChoice::Claude),
+            "@/test/dir/PROGRESS.md @/test/dir/PRD.md @~/.agents/AGENTS.md \
+            1. Find the highest-priority task and implement it. \
+            2. Run your tests and type checks. \
+            3. Update the PRD with what was done. \
+            4. Append your progress to PROGRESS.md. \
+            5. Commit your changes. \
+            ONLY WORK ON A SINGLE TASK. \
+            YOU **MUST** OBEY RULES SPECIFIED IN @~/.agents/AGENTS.md. \
+            If the PRD is complete, output <promise>COMPLETE</promise>."
+        );
+    }
+
+    #[test]
+    fn test_render_variables() {
+        let ctx = ctx(Path::new("/tasks/login"));
+        let template = PromptTemplate {
+            text:
+                "{{task}} via {{ tool }}: {{iteration}}/{{iterations}}, {{remaining}} left. {{prd}}"
+                    .into(),
+            source: None,
+        };
+
+        assert_eq!(
+            template
+                .render(&vars(&ctx, &ToolChoice::Codex, &[]))
+                .unwrap(),
+            "login via codex: 2/5, 3 left. /tasks/login/PRD.md"
+        );
+        assert!(template
+            .render(&vars(&ctx, &ToolChoice::Claude, &[]))
+            .unwrap()
+            .ends_with("@/tasks/login/PRD.md"));
+    }
+
+    #[test]
+    fn test_substitute_errors() {
+        assert!(substitute("{{nope}}", &|_| None)
+            .unwrap_err()
+            .contains("nope"));
+        assert!(substitute("{{prd", &|_| Some(String::new())).is_err());
+        assert!(substitute("{{#prd}}x", &|_| Some(String::new())).is_err());
+        assert_eq!(substitute("no vars", &|_| None).unwrap(), "no vars");
+    }
+
+    #[test]
+    fn test_substitute_sections() {
+        let lookup = |name: &str| match name {
+            "full" => Some("yes".to_string()),
+            "empty" => Some(String::new()),
+            _ => None,
+        };
+        assert_eq!(
+            substitute(
+                "a{{#full}} [{{full}}]{{/full}}{{#empty}} gone{{/empty}} b",
+                &lookup
+            )
+            .unwrap(),
+            "a [yes] b"
+        );
+        assert_eq!(
+            substitute("{{^full}}gone{{/full}}{{^empty}}kept{{/empty}}", &lookup).unwrap(),
+            "kept"
+        );
+    }
+
+    #[test]
+    fn test_default_prompt_with_selected_item() {
+        let ctx = ctx(Path::new("/test/dir"));
+        let item = PrdItem {
+            text: "Add login form".into(),
+            checked: false,
+            depth: 0,
+            section: None,
+            line: 7,
+        };
+        let prompt = PromptTemplate::default()
+            .render(&PromptVars {
+                item: Some(&item),
+                ..vars(&ctx, &ToolChoice::Claude, &[])
+            })
+            .unwrap();
+
+        assert!(prompt.contains("1. Implement this PRD item (line 7): Add login form"));
+        assert!(!prompt.contains("highest-priority"));
+        assert!(prompt.contains("done. 2. Run your tests"));
+    }
+
+    #[test]
+    fn test_default_prompt_without_rules() {
+        let ctx = ctx(Path::new("/test/dir"));
+        let prompt = PromptTemplate::default()
+            .render(&vars(&ctx, &ToolChoice::Claude, &[]))
+            .unwrap();
+
+        assert!(prompt.starts_with("@/test/dir/PROGRESS.md @/test/dir/PRD.md 1."));
+        assert!(!prompt.contains("OBEY RULES"));
+        assert!(prompt.contains("SINGLE TASK. If the PRD is complete"));
+    }
+
+    #[test]
+    fn test_multiple_rules_in_native_style() {
+        let ctx = ctx(Path::new("/test/dir"));
+        let mut rules = global_rules();
+        rules.push(RulesFile {
+            display: "AGENTS.md".into(),
+            path: PathBuf::from("AGENTS.md"),
+        });
+        let template = PromptTemplate {
+            text: "{{rules}}".into(),
+            source: None,
+        };
+
+        assert_eq!(
+            template
+                .render(&vars(&ctx, &ToolChoice::Claude, &rules))
+                .unwrap(),
+            "@~/.agents/AGENTS.md @AGENTS.md"
+        );
+        assert_eq!(
+            template
+                .render(&vars(&ctx, &ToolChoice::Codex, &rules))
+                .unwrap(),
+            "~/.agents/AGENTS.md, AGENTS.md"
+        );
+    }
+
+    #[test]
+    fn test_load_prefers_task_template() {
+        let temp = tempdir().unwrap();
+        let ctx = ctx(temp.path());
+        assert!(PromptTemplate::load(&ctx).unwrap().source().is_none());
+
+        fs::write(temp.path().join(TASK_PROMPT_FILE), "Work on {{prd_path}}\n").unwrap();
+        let template = PromptTemplate::load(&ctx).unwrap();
+        assert_eq!(
+            template.source(),
+            Some(temp.path().join(TASK_PROMPT_FILE).as_path())
+        );
+        assert_eq!(
+            template
+                .render(&vars(&ctx, &ToolChoice::Claude, &[]))
+                .unwrap(),
+            format!("Work on {}", ctx.prd_file.display())
+        );
+    }
+
+    #[test]
+    fn test_from_file_rejects_unknown_variable() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(TASK_PROMPT_FILE);
+        fs::write(&path, "{{prd_file}}").unwrap();
+
+        assert!(matches!(
+            PromptTemplate::from_file(&path),
+            Err(RalphError::InvalidPrompt { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_file_checks_every_section() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join(TASK_PROMPT_FILE);
+        for text in ["{{^item}}{{bogus}}{{/item}}", "{{#item}}{{bogus}}{{/item}}"] {
+            fs::write(&path, text).unwrap();
+            assert!(matches!(
+                PromptTemplate::from_file(&path),
+                Err(RalphError::InvalidPrompt { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use crate::error::{RalphError, Result};
+use crate::outcome::RunOutcome;
+use crate::status::TaskStatus;
+use crate::task::{TaskContext, TASKS_ROOT};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Optional YAML block at the top of a PRD, between `---` lines; keys other
+/// tools put there are ignored
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct FrontMatter {
+    /// Task names that must be complete before this one starts
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Lower runs first among tasks whose dependencies are met; unset runs last
+    pub priority: Option<i64>,
+}
+
+impl FrontMatter {
+    pub fn read(prd_file: &Path) -> Result<Self> {
+        let content = fs::read_to_string(prd_file).map_err(|e| RalphError::PrdRead {
+            path: prd_file.to_path_buf(),
+            source: e,
+        })?;
+        Self::parse(&content).map_err(|message| RalphError::InvalidFrontMatter {
+            path: prd_file.to_path_buf(),
+            message,
+        })
+    }
+
+    /// Front matter of `content`, or the default when it has none
+    pub fn parse(content: &str) -> std::result::Result<Self, String> {
+        let Some(rest) = content
+            .strip_prefix("---\n")
+            .or_else(|| content.strip_prefix("---\r\n"))
+        else {
+            return Ok(Self::default());
+        };
+        let end = rest
+            .lines()
+            .position(|line| line.trim_end() == "---")
+            .ok_or("no closing `---` line")?;
+        let yaml: Vec<&str> = rest.lines().take(end).collect();
+        if yaml.iter().all(|line| line.trim().is_empty()) {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(&yaml.join("\n")).map_err(|e| e.to_string())
+    }
+}
+
+/// A task waiting its turn in a multi-task run
+pub struct QueuedTask {
+    /// Task directory name, as used in `depends_on`
+    pub name: String,
+    pub ctx: TaskContext,
+    pub front: FrontMatter,
+}
+
+impl QueuedTask {
+    fn load(ctx: TaskContext) -> Result<Self> {
+        let name = ctx
+            .dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| ctx.dir.display().to_string());
+        let front = FrontMatter::read(&ctx.prd_file)?;
+        Ok(Self { name, ctx, front })
+    }
+}
+
+/// The tasks named with `-t` (or every unfinished task for `--all`), in run order
+pub fn build(tasks: &[String], all: bool) -> Result<Vec<QueuedTask>> {
+    let contexts = if all {
+        let mut contexts = Vec::new();
+        for dir in TaskContext::list(Path::new(TASKS_ROOT)) {
+            let status = TaskStatus::load(&dir);
+            if status.completed {
+                println!("Skipping {}: already complete", status.name);
+            } else if status.prd_file.is_none() {
+                println!("Skipping {}: no PRD file", status.name);
+            } else {
+                contexts.push(TaskContext::discover(&dir.to_string_lossy())?);
+            }
+        }
+        contexts
+    } else {
+        tasks
+            .iter()
+            .map(|task| TaskContext::discover_from_prd(task))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let queued = contexts
+        .into_iter()
+        .map(QueuedTask::load)
+        .collect::<Result<Vec<_>>>()?;
+    order(queued, |name| {
+        let dir = Path::new(TASKS_ROOT).join(name);
+        dir.is_dir().then(|| TaskStatus::load(&dir).completed)
+    })
+}
+
+/// Topologically sort `tasks` so every task comes after its dependencies.
+/// Among tasks that are ready, the lowest `priority` goes first, then the
+/// order they were given in. A dependency outside the queue must already be
+/// complete; `is_complete` returns `None` for a task that does not exist.
+pub fn order(
+    tasks: Vec<QueuedTask>,
+    is_complete: impl Fn(&str) -> Option<bool>,
+) -> Result<Vec<QueuedTask>> {
+    let names: HashSet<String> = tasks.iter().map(|t| t.name.clone()).collect();
+    for task in &tasks {
+        for dep in &task.front.depends_on {
+            if names.contains(dep.as_str()) {
+                continue;
+            }
+            let message = match is_complete(dep) {
+                Some(true) => continue,
+                Some(false) => format!("'{}', which is not complete; queue it too", dep),
+                None => format!("'{}', but there is no such task in {}", dep, TASKS_ROOT),
+            };
+            return Err(RalphError::TaskDependency {
+                task: task.name.clone(),
+                message,
+            });
+        }
+    }
+
+    let mut pending: Vec<Option<QueuedTask>> = tasks.into_iter().map(Some).collect();
+    let mut done: HashSet<String> = HashSet::new();
+    let mut ordered = Vec::new();
+    while ordered.len() < pending.len() {
+        let next = pending
+            .iter()
+            .enumerate()
+            .filter_map(|(i, task)| Some((i, task.as_ref()?)))
+            .filter(|(_, task)| {
+                task.front
+                    .depends_on
+                    .iter()
+                    .all(|dep| done.contains(dep) || !names.contains(dep.as_str()))
+            })
+            .min_by_key(|(i, task)| (task.front.priority.unwrap_or(i64::MAX), *i))
+            .map(|(i, _)| i);
+
+        let Some(i) = next else {
+            let mut stuck: Vec<&str> = pending
+                .iter()
+                .flatten()
+                .map(|task| task.name.as_str())
+                .collect();
+            stuck.sort();
+            return Err(RalphError::DependencyCycle {
+                tasks: stuck.join(", "),
+            });
+        };
+        let task = pending[i].take().expect("picked a pending task");
+        done.insert(task.name.clone());
+        ordered.push(task);
+    }
+    Ok(ordered)
+}
+
+/// Names of queued tasks that can no longer run because `failed` (or a task
+/// depending on it) did not complete
+pub fn blocked_by<'a>(failed: &str, tasks: &'a [QueuedTask]) -> Vec<&'a str> {
+    let mut blocked: Vec<&str> = Vec::new();
+    for task in tasks {
+        let depends_on = |name: &str| task.front.depends_on.iter().any(|dep| dep == name);
+        if depends_on(failed) || blocked.iter().any(|name| depends_on(name)) {
+            blocked.push(&task.name);
+        }
+    }
+    blocked
+}
+
+/// Whether the rest of the queue can go on after a task ended this way;
+/// tasks that don't depend on an exhausted or stalled task still run
+pub fn should_continue(outcome: &RunOutcome) -> bool {
+    matches!(
+        outcome,
+        RunOutcome::Complete { .. } | RunOutcome::Exhausted { .. } | RunOutcome::Stalled { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::tempdir;
+
+    fn task(name: &str, depends_on: &[&str], priority: Option<i64>) -> QueuedTask {
+        QueuedTask {
+            name: name.to_string(),
+            ctx: TaskContext {
+                dir: PathBuf::from(name),
+                prd_file: PathBuf::from(name).join("PRD.md"),
+                progress_file: PathBuf::from(name).join("PROGRESS.md"),
+            },
+            front: FrontMatter {
+                depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+                priority,
+            },
+        }
+    }
+
+    fn names(tasks: &[QueuedTask]) -> Vec<&str> {
+        tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_front_matter() {
+        let prd = "---\ndepends_on: [schema, api]\npriority: 1\n---\n# PRD\n- [ ] item\n";
+        assert_eq!(
+            FrontMatter::parse(prd).unwrap(),
+            FrontMatter {
+                depends_on: vec!["schema".into(), "api".into()],
+                priority: Some(1),
+            }
+        );
+
+        let list = "---\ndepends_on:\n  - schema\n---\n";
+        assert_eq!(FrontMatter::parse(list).unwrap().depends_on, ["schema"]);
+    }
+
+    #[test]
+    fn test_parse_front_matter_from_other_tools() {
+        let crlf = "---\r\ndepends_on: [schema]\r\n---\r\n# PRD\r\n";
+        assert_eq!(FrontMatter::parse(crlf).unwrap().depends_on, ["schema"]);
+
+        let extra = "---\ntitle: Login\ntags: [auth]\npriority: 2\n---\n";
+        assert_eq!(FrontMatter::parse(extra).unwrap().priority, Some(2));
+    }
+
+    #[test]
+    fn test_parse_without_front_matter() {
+        assert_eq!(
+            FrontMatter::parse("# PRD\n---\n").unwrap(),
+            FrontMatter::default()
+        );
+        assert_eq!(
+            FrontMatter::parse("---\n---\n# PRD\n").unwrap(),
+            FrontMatter::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_front_matter() {
+        assert!(FrontMatter::parse("---\ndepends_on: [a\n---\n").is_err());
+        assert!(FrontMatter::parse("---\npriority: 1\n").is_err());
+    }
+
+    #[test]
+    fn test_read_unreadable_prd_is_an_error() {
+        let temp = tempdir().unwrap();
+        assert!(matches!(
+            FrontMatter::read(&temp.path().join("PRD.md")),
+            Err(RalphError::PrdRead { .. })
+        ));
+    }
+
+    #[test]
+    fn test_order_follows_dependencies() {
+        let tasks = vec![
+            task("ui", &["api"], None),
+            task("api", &["schema"], None),
+            task("schema", &[], None),
+        ];
+        let ordered = order(tasks, |_| None).unwrap();
+        assert_eq!(names(&ordered), ["schema", "api", "ui"]);
+    }
+
+    #[test]
+    fn test_order_uses_priority_then_given_order() {
+        let tasks = vec![
+            task("docs", &[], None),
+            task("cleanup", &[], Some(2)),
+            task("hotfix", &[], Some(0)),
+            task("readme", &[], None),
+        ];
+        let ordered = order(tasks, |_| None).unwrap();
+        assert_eq!(names(&ordered), ["hotfix", "cleanup", "docs", "readme"]);
+    }
+
+    #[test]
+    fn test_order_detects_cycles() {
+        let tasks = vec![
+            task("a", &["b"], None),
+            task("b", &["a"], None),
+            task("c", &[], None),
+        ];
+        match order(tasks, |_| None) {
+            Err(RalphError::DependencyCycle { tasks }) => assert_eq!(tasks, "a, b"),
+            _ => panic!("Expected DependencyCycle error"),
+        }
+    }
+
+    #[test]
+    fn test_dependencies_outside_the_queue() {
+        let complete = |name: &str| match name {
+            "done" => Some(true),
+            "open" => Some(false),
+            _ => None,
+        };
+        assert!(order(vec![task("a", &["done"], None)], complete).is_ok());
+        for dep in ["open", "missing"] {
+            match order(vec![task("a", &[dep], None)], complete) {
+                Err(RalphError::TaskDependency { task, message }) => {
+                    assert_eq!(task, "a");
+                    assert!(message.contains(dep));
+                }
+                _ => panic!("Expected TaskDependency error for {}", dep),
+            }
+        }
+    }
+
+    #[test]
+    fn test_blocked_by_is_transitive() {
+        let tasks = vec![
+            task("api", &["schema"], None),
+            task("docs", &[], None),
+            task("ui", &["api"], None),
+        ];
+        assert_eq!(blocked_by("schema", &tasks), ["api", "ui"]);
+        assert!(blocked_by("docs", &tasks).is_empty());
+    }
+}
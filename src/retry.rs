@@ -0,0 +1,111 @@
+use crate::error::RalphError;
+use crate::tools::ToolOutput;
+use std::io::ErrorKind;
+use std::time::Duration;
+
+/// How often a failed tool invocation is retried within one iteration
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts per iteration, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+/// Spawn failures are transient unless the binary is missing or not executable
+pub fn is_retryable_error(err: &RalphError) -> bool {
+    match err {
+        RalphError::ToolFailed { source, .. } => !matches!(
+            source.kind(),
+            ErrorKind::NotFound | ErrorKind::PermissionDenied
+        ),
+        _ => false,
+    }
+}
+
+/// Abnormal exits are retried; timeouts and interrupts are final for the iteration
+pub fn is_retryable_output(output: &ToolOutput) -> bool {
+    !output.status.success() && !output.timed_out && !output.interrupted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::ExitStatus;
+
+    fn output(code: i32) -> ToolOutput {
+        ToolOutput {
+            status: ExitStatus::from_raw(code << 8),
+            stdout: String::new(),
+            stderr: String::new(),
+            timed_out: false,
+            interrupted: false,
+            events: Vec::new(),
+        }
+    }
+
+    fn tool_failed(kind: ErrorKind) -> RalphError {
+        RalphError::ToolFailed {
+            tool: "claude".into(),
+            source: io::Error::from(kind),
+        }
+    }
+
+    #[test]
+    fn test_delay_doubles_and_caps() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            backoff: Duration::from_secs(10),
+            max_backoff: Duration::from_secs(30),
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(10));
+        assert_eq!(policy.delay(2), Duration::from_secs(20));
+        assert_eq!(policy.delay(3), Duration::from_secs(30));
+        assert_eq!(policy.delay(40), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_missing_binary_is_fatal() {
+        assert!(!is_retryable_error(&tool_failed(ErrorKind::NotFound)));
+        assert!(!is_retryable_error(&tool_failed(
+            ErrorKind::PermissionDenied
+        )));
+        assert!(is_retryable_error(&tool_failed(ErrorKind::Interrupted)));
+        assert!(!is_retryable_error(&RalphError::UserCancelled));
+    }
+
+    #[test]
+    fn test_retryable_output() {
+        assert!(!is_retryable_output(&output(0)));
+        assert!(is_retryable_output(&output(1)));
+
+        let mut timed_out = output(1);
+        timed_out.timed_out = true;
+        assert!(!is_retryable_output(&timed_out));
+
+        let mut interrupted = output(1);
+        interrupted.interrupted = true;
+        assert!(!is_retryable_output(&interrupted));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::config::RulesConfig;
+use crate::task::TaskContext;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// User-wide rules file
+const GLOBAL_RULES_FILE: &str = "~/.agents/AGENTS.md";
+
+/// Rules file names looked up in the repo root and the task directory
+const RULES_FILE_NAMES: &[&str] = &["AGENTS.md", "CLAUDE.md"];
+
+/// A rules file the agent must obey
+#[derive(Debug, Clone, PartialEq)]
+pub struct RulesFile {
+    /// Path as shown to the agent (`~` is kept unexpanded)
+    pub display: String,
+    /// Path on disk
+    pub path: PathBuf,
+}
+
+impl RulesFile {
+    fn new(display: &str) -> Self {
+        Self {
+            display: display.to_string(),
+            path: expand_home(display),
+        }
+    }
+}
+
+/// Rules files for a task, in order: global, repo root, task directory, then
+/// `include` entries from config, minus `exclude` entries. Discovered files
+/// that do not exist are skipped; missing `include` entries produce a warning.
+pub fn discover(ctx: &TaskContext, config: &RulesConfig) -> Vec<RulesFile> {
+    let mut candidates = Vec::new();
+    if config.discover.unwrap_or(true) {
+        candidates.push(RulesFile::new(GLOBAL_RULES_FILE));
+        for dir in [Path::new(""), ctx.dir.as_path()] {
+            for name in RULES_FILE_NAMES {
+                candidates.push(RulesFile::new(&dir.join(name).display().to_string()));
+            }
+        }
+        candidates.retain(|file| file.path.is_file());
+    }
+
+    for entry in &config.include {
+        let file = RulesFile::new(entry);
+        if file.path.is_file() {
+            candidates.push(file);
+        } else {
+            eprintln!("Warning: rules file {} not found, skipping", entry);
+        }
+    }
+
+    let excluded: Vec<PathBuf> = config
+        .exclude
+        .iter()
+        .map(|entry| normalize(&expand_home(entry)))
+        .collect();
+
+    let mut files: Vec<RulesFile> = Vec::new();
+    for file in candidates {
+        let path = normalize(&file.path);
+        if excluded.contains(&path) || files.iter().any(|f| normalize(&f.path) == path) {
+            continue;
+        }
+        files.push(file);
+    }
+    files
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Canonical form for comparing paths, falling back to the path itself
+fn normalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn ctx(dir: &Path) -> TaskContext {
+        TaskContext {
+            dir: dir.to_path_buf(),
+            prd_file: dir.join("PRD.md"),
+            progress_file: dir.join("PROGRESS.md"),
+        }
+    }
+
+    fn displays(files: &[RulesFile]) -> Vec<&str> {
+        files.iter().map(|f| f.display.as_str()).collect()
+    }
+
+    #[test]
+    fn test_discover_task_rules_and_includes() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("CLAUDE.md"), "rules").unwrap();
+        fs::write(temp.path().join("STYLE.md"), "style").unwrap();
+        let config = RulesConfig {
+            discover: Some(true),
+            include: vec![temp.path().join("STYLE.md").display().to_string()],
+            exclude: Vec::new(),
+        };
+
+        let files = discover(&ctx(temp.path()), &config);
+        let task_rules = temp.path().join("CLAUDE.md").display().to_string();
+        let style = temp.path().join("STYLE.md").display().to_string();
+        assert!(displays(&files).ends_with(&[task_rules.as_str(), style.as_str()]));
+    }
+
+    #[test]
+    fn test_discover_skips_missing_and_excluded() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("AGENTS.md"), "rules").unwrap();
+        let agents = temp.path().join("AGENTS.md").display().to_string();
+        let config = RulesConfig {
+            discover: Some(false),
+            include: vec![
+                agents.clone(),
+                temp.path().join("MISSING.md").display().to_string(),
+            ],
+            exclude: Vec::new(),
+        };
+        assert_eq!(
+            displays(&discover(&ctx(temp.path()), &config)),
+            [agents.as_str()]
+        );
+
+        let config = RulesConfig {
+            exclude: vec![agents.clone()],
+            ..config
+        };
+        assert!(discover(&ctx(temp.path()), &config).is_empty());
+    }
+
+    #[test]
+    fn test_discover_deduplicates() {
+        let temp = tempdir().unwrap();
+        fs::write(temp.path().join("AGENTS.md"), "rules").unwrap();
+        let agents = temp.path().join("AGENTS.md").display().to_string();
+        let config = RulesConfig {
+            discover: Some(false),
+            include: vec![agents.clone(), agents.clone()],
+            exclude: Vec::new(),
+        };
+        assert_eq!(discover(&ctx(temp.path()), &config).len(), 1);
+    }
+
+    #[test]
+    fn test_expand_home() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(
+            expand_home("~/.agents/AGENTS.md"),
+            Path::new(&home).join(".agents/AGENTS.md")
+        );
+        assert_eq!(expand_home("AGENTS.md"), PathBuf::from("AGENTS.md"));
+    }
+}
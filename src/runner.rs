@@ -1,177 +1,776 @@
-use crate::cli::{Cli, ToolChoice};
+use crate::budget::Budget;
+use crate::cli::{ResumeArgs, RunArgs};
+use crate::config::{Config, RunConfig};
 use crate::error::{RalphError, Result};
-use crate::models::{default_model, resolve_model};
+use crate::escalation::Escalation;
+use crate::git::{self, Checkpoint};
+use crate::manifest::{find_resumable, GitRecord, ItemRecord, IterationDetails, RunRecorder};
+use crate::outcome::RunOutcome;
+use crate::parallel;
+use crate::prd::{self, PrdItem, PrdProgress};
+use crate::prompt::{PromptTemplate, PromptVars};
+use crate::queue::{self, QueuedTask};
+use crate::retry;
+use crate::rules::{self, RulesFile};
+use crate::signals::{self, Interrupt};
+use crate::stall::{self, IterationChanges, StallAction, StallDetector};
 use crate::task::TaskContext;
-use crate::tools::create_tool;
-use std::io::{self, Write};
+use crate::tools::{create_tool, Tool, ToolOutput, Usage};
+use crate::verify;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
-const COMPLETION_MARKER: &str = "<promise>COMPLETE</promise>";
+pub fn run(cli: RunArgs) -> Result<RunOutcome> {
+    if cli.all || cli.task.len() > 1 {
+        return run_queue(&cli);
+    }
 
-pub fn run(cli: Cli) -> Result<()> {
     // Validation: Check if both flags are provided
-    if cli.dir.is_some() && cli.task.is_some() {
+    if cli.dir.is_some() && !cli.task.is_empty() {
         eprintln!("Warning: Both --dir and --task provided. Ignoring --dir.");
     }
 
     // Validation: Check if neither flag is provided
-    if cli.dir.is_none() && cli.task.is_none() {
+    if cli.dir.is_none() && cli.task.is_empty() {
         return Err(RalphError::NoInputProvided);
     }
 
     // Dispatch: Prefer --task over --dir when both are present
-    let ctx = if let Some(ref task) = cli.task {
+    let ctx = if let Some(task) = cli.task.first() {
         TaskContext::discover_from_prd(task)?
     } else {
         TaskContext::discover(cli.dir.as_ref().unwrap())?
     };
 
-    if cli.variant.is_some() && !matches!(cli.tool, ToolChoice::OpenCode) {
-        return Err(RalphError::InvalidToolOption {
-            tool: cli.tool.to_string(),
-        });
+    let config = Config::load()?;
+    let task = TaskRun::prepare(ctx, &cli, &config)?;
+    task.print_summary(&config);
+    confirm_or_skip(cli.yes)?;
+    task.start()
+}
+
+/// Run queued tasks one after another, each to the end of its run, in
+/// dependency order. A task that does not complete blocks the tasks depending
+/// on it; an interrupt, the budget or an error stops the whole queue.
+fn run_queue(cli: &RunArgs) -> Result<RunOutcome> {
+    let queue = queue::build(&cli.task, cli.all)?;
+    if queue.is_empty() {
+        println!("Nothing to run: every task is complete");
+        return Ok(RunOutcome::Complete { iterations: 0 });
+    }
+
+    // Resolve every task first so a bad PRD or prompt stops the queue before it starts
+    let config = Config::load()?;
+    let mut runs = Vec::new();
+    for task in &queue {
+        runs.push(TaskRun::prepare(task.ctx.clone(), cli, &config)?);
+    }
+
+    println!("Task queue:");
+    for (i, task) in queue.iter().enumerate() {
+        match task.front.depends_on.as_slice() {
+            [] => println!("  {}. {}", i + 1, task.name),
+            deps => println!("  {}. {} (after {})", i + 1, task.name, deps.join(", ")),
+        }
+    }
+    println!();
+    for run in &runs {
+        run.print_summary(&config);
+    }
+    confirm_or_skip(cli.yes)?;
+
+    if let Some(jobs) = cli.parallel.filter(|&jobs| jobs > 1) {
+        return parallel::run(cli, jobs as usize, &queue);
+    }
+
+    let mut results: HashMap<&str, String> = HashMap::new();
+    let mut blocked: HashSet<&str> = HashSet::new();
+    let mut failure = None;
+    let mut iterations = 0;
+    for (i, (task, run)) in queue.iter().zip(runs).enumerate() {
+        if blocked.contains(task.name.as_str()) {
+            results.insert(&task.name, "skipped (depends on an unfinished task)".into());
+            continue;
+        }
+        println!("\n=== Task {}/{}: {} ===", i + 1, queue.len(), task.name);
+        let outcome = match run.start() {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                results.insert(&task.name, "failed".into());
+                print_queue_summary(&queue, &results);
+                return Err(e);
+            }
+        };
+
+        iterations += outcome.iterations();
+        results.insert(
+            &task.name,
+            format!(
+                "{} after {} iteration(s)",
+                outcome.label(),
+                outcome.iterations()
+            ),
+        );
+        if matches!(outcome, RunOutcome::Complete { .. }) {
+            continue;
+        }
+        blocked.extend(queue::blocked_by(&task.name, &queue[i + 1..]));
+        if !queue::should_continue(&outcome) {
+            failure = Some(outcome);
+            break;
+        }
+        failure.get_or_insert(outcome);
+    }
+
+    print_queue_summary(&queue, &results);
+    Ok(failure.unwrap_or(RunOutcome::Complete { iterations }))
+}
+
+fn print_queue_summary(queue: &[QueuedTask], results: &HashMap<&str, String>) {
+    let width = queue.iter().map(|t| t.name.len()).max().unwrap_or(0);
+    println!("\nQueue summary:");
+    for task in queue {
+        let result = results
+            .get(task.name.as_str())
+            .map_or("not run", |r| r.as_str());
+        println!("  {:<width$}  {}", task.name, result);
+    }
+}
+
+/// A fresh run of one task, resolved and ready to start
+struct TaskRun {
+    ctx: TaskContext,
+    run: RunConfig,
+    rules: Vec<RulesFile>,
+    tool: Box<dyn Tool>,
+    template: PromptTemplate,
+}
+
+impl TaskRun {
+    fn prepare(ctx: TaskContext, cli: &RunArgs, config: &Config) -> Result<Self> {
+        let run = RunConfig::resolve(cli, config)?;
+        let rules = rules::discover(&ctx, &config.rules);
+        let tool = create_tool(&run, &rules);
+        let template = PromptTemplate::load(&ctx)?;
+        Ok(Self {
+            ctx,
+            run,
+            rules,
+            tool,
+            template,
+        })
+    }
+
+    fn print_summary(&self, config: &Config) {
+        print_summary(
+            &self.ctx,
+            &self.run,
+            self.tool.as_ref(),
+            &self.template,
+            &self.rules,
+            &config.sources,
+        );
+    }
+
+    fn start(self) -> Result<RunOutcome> {
+        let prompt = self.template.render(&PromptVars {
+            ctx: &self.ctx,
+            tool: &self.run.tool,
+            rules: &self.rules,
+            iteration: 1,
+            iterations: self.run.iterations,
+            item: None,
+            completion_marker: &self.run.completion_marker(),
+        })?;
+        let mut recorder = RunRecorder::create(&self.ctx, &self.run, &prompt)?;
+        println!("Recording run to {}", recorder.dir().display());
+
+        let outcome = run_loop(
+            &self.run,
+            &self.ctx,
+            self.tool,
+            &self.template,
+            &self.rules,
+            &mut recorder,
+        )?;
+        print_run_summary(&recorder);
+        recorder.finish(&outcome)?;
+        Ok(outcome)
     }
+}
+
+/// Continue an interrupted run in its original run directory
+pub fn resume(args: &ResumeArgs) -> Result<RunOutcome> {
+    let task_dir = args
+        .task
+        .as_deref()
+        .map(TaskContext::resolve_directory)
+        .transpose()?;
+    let (run_dir, manifest) =
+        find_resumable(task_dir.as_deref()).ok_or(RalphError::NothingToResume)?;
+
+    let ctx = TaskContext {
+        dir: manifest.task_dir.clone(),
+        prd_file: manifest.prd_file.clone(),
+        progress_file: manifest.progress_file.clone(),
+    };
+    let config = Config::load()?;
+    let mut run = RunConfig::for_resume(&manifest, &config)?;
+    run.max_cost = args.max_cost.or(run.max_cost);
+    run.max_tokens = args.max_tokens.or(run.max_tokens);
 
-    let model = cli
-        .model
-        .map(|m| resolve_model(&cli.tool, &m))
-        .or_else(|| default_model(&cli.tool));
+    let rules = rules::discover(&ctx, &config.rules);
+    let tool = create_tool(&run, &rules);
+    let mut recorder = RunRecorder::open(&run_dir)?;
 
-    let tool = create_tool(&cli.tool, model.clone(), cli.variant.clone());
+    println!("Resuming run {}", run_dir.display());
+    println!(
+        "  Iterations:    {} of {} done",
+        recorder.last_iteration(),
+        run.iterations
+    );
+    let template = PromptTemplate::load(&ctx)?;
+    print_summary(
+        &ctx,
+        &run,
+        tool.as_ref(),
+        &template,
+        &rules,
+        &config.sources,
+    );
+    confirm_or_skip(args.yes)?;
 
+    let outcome = run_loop(&run, &ctx, tool, &template, &rules, &mut recorder)?;
+    print_run_summary(&recorder);
+    recorder.finish(&outcome)?;
+    Ok(outcome)
+}
+
+fn print_summary(
+    ctx: &TaskContext,
+    run: &RunConfig,
+    tool: &dyn Tool,
+    template: &PromptTemplate,
+    rules: &[RulesFile],
+    sources: &[PathBuf],
+) {
     println!("Found files:");
     println!("  Task dir:      {}", ctx.dir.display());
-    println!("  PRD file:      {}", ctx.prd_file.display());
+    match PrdProgress::read(&ctx.prd_file) {
+        Some(progress) => println!(
+            "  PRD file:      {} ({} items done)",
+            ctx.prd_file.display(),
+            progress
+        ),
+        None => println!("  PRD file:      {}", ctx.prd_file.display()),
+    }
     println!("  PROGRESS file: {}", ctx.progress_file.display());
     println!("  Tool:          {}", tool.name());
-    if let Some(ref m) = model {
+    if !run.model_chain.is_empty() {
+        let models: Vec<&str> = run.model_chain.iter().map(|s| s.model.as_str()).collect();
+        println!(
+            "  Models:        {} (escalating after {} failed iterations or a stall)",
+            models.join(" -> "),
+            run.escalate_after
+        );
+    } else if let Some(ref m) = run.model {
         println!("  Model:         {}", m);
     }
-    if let Some(ref v) = cli.variant {
+    if let Some(ref v) = run.variant {
         println!("  Variant:       {}", v);
     }
+    if let Some(path) = template.source() {
+        println!("  Prompt:        {}", path.display());
+    }
+    for file in rules {
+        println!("  Rules:         {}", file.display);
+    }
+    if run.select_item {
+        println!("  PRD items:     selected by ralph, one per iteration");
+    }
+    if let Some(timeout) = run.iteration_timeout {
+        println!("  Timeout:       {}s per iteration", timeout.as_secs());
+    }
+    for command in &run.verify {
+        println!("  Verify:        {}", command);
+    }
+    if let Some(max_cost) = run.max_cost {
+        match run.price {
+            Some(_) => println!("  Max cost:      ${:.2}", max_cost),
+            None => println!(
+                "  Max cost:      ${:.2} (no known price for this model; enforced only if the tool reports cost)",
+                max_cost
+            ),
+        }
+    }
+    if let Some(max_tokens) = run.max_tokens {
+        println!("  Max tokens:    {}", max_tokens);
+    }
+    for source in sources {
+        println!("  Config:        {}", source.display());
+    }
     println!();
+}
 
-    if !confirm_proceed()? {
+/// Ask before running unless `--yes` was given; refuse to block on a non-terminal stdin
+fn confirm_or_skip(yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !io::stdin().is_terminal() {
+        return Err(RalphError::NotInteractive);
+    }
+    if !confirm("Proceed with these files?")? {
         return Err(RalphError::UserCancelled);
     }
+    Ok(())
+}
+
+fn run_loop(
+    run: &RunConfig,
+    ctx: &TaskContext,
+    mut tool: Box<dyn Tool>,
+    template: &PromptTemplate,
+    rules: &[RulesFile],
+    recorder: &mut RunRecorder,
+) -> Result<RunOutcome> {
+    // Ctrl-C now stops the loop gracefully instead of killing ralph mid-iteration
+    signals::install();
 
-    let prompt = build_prompt(&ctx, &cli.tool);
+    // Carried into the next prompt when a completion claim fails verification
+    let mut note: Option<String> = None;
 
-    for i in 1..=cli.iterations {
-        println!("\n--- Iteration {}/{} ---\n", i, cli.iterations);
+    let mut stalls = StallDetector::new(&run.stall);
+    // The nudge action is tried once; a stall after that aborts
+    let mut nudged = false;
 
-        let (status, output) = tool.run(&ctx, &prompt)?;
+    // The model (and price) change as the run moves along an escalation chain
+    let mut run = run.clone();
+    let mut escalation = Escalation::new(
+        &run.model_chain,
+        run.escalate_after,
+        recorder.escalation_level(),
+    );
 
-        if !status.success() {
-            eprintln!("Tool exited with non-zero status: {:?}", status.code());
+    // Resumed runs count what earlier iterations already spent
+    let mut budget = Budget::new(run.max_cost, run.max_tokens);
+    recorder
+        .recorded_usage()
+        .for_each(|usage| budget.record(usage));
+    let mut warned_unpriced = false;
+    let completion_marker = run.completion_marker();
+
+    // Resumed runs continue after the last recorded iteration
+    for i in recorder.last_iteration() + 1..=run.iterations {
+        if let Some(reason) = budget.exceeded() {
+            println!(
+                "\nStopping before iteration {}: {}. Raise the budget and continue with `ralph resume`.",
+                i, reason
+            );
+            return Ok(RunOutcome::OverBudget { iterations: i - 1 });
+        }
+
+        println!("\n--- Iteration {}/{} ---\n", i, run.iterations);
+
+        let items_before = prd::read_items(&ctx.prd_file);
+        let prd_before = PrdProgress::count(&items_before);
+        if let Some(progress) = prd_before {
+            println!("PRD progress: {} items done\n", progress);
         }
 
-        if output.contains(COMPLETION_MARKER) {
+        let item = if run.select_item {
+            select_item(&items_before)
+        } else {
+            None
+        };
+        let base_prompt = template.render(&PromptVars {
+            ctx,
+            tool: &run.tool,
+            rules,
+            iteration: i,
+            iterations: run.iterations,
+            item: item.as_ref(),
+            completion_marker: &completion_marker,
+        })?;
+        let prompt = match note.take() {
+            Some(note) => format!("{}\n\n{}", base_prompt, note),
+            None => base_prompt.to_string(),
+        };
+
+        let task_files_before = task_files_fingerprint(ctx);
+
+        let checkpoint = Checkpoint::create(Path::new("."))?;
+        let started_at = Utc::now();
+        let (output, attempts) =
+            run_with_retry(&run, ctx, tool.as_ref(), &prompt, checkpoint.as_ref())?;
+
+        let failed = !output.status.success();
+        if output.interrupted {
+            eprintln!("Tool stopped by interrupt");
+        } else if output.timed_out {
+            eprintln!(
+                "Iteration timed out after {}s; tool stopped, moving on",
+                run.iteration_timeout.unwrap_or_default().as_secs()
+            );
+        } else if failed {
+            eprintln!(
+                "Tool exited with non-zero status: {:?}",
+                output.status.code()
+            );
+        }
+        if let Some(error) = output.last_error() {
+            eprintln!("Tool reported an error: {}", error);
+        }
+        let usage = iteration_usage(&run, &output);
+        match usage {
+            Some(ref usage) => {
+                budget.record(usage);
+                println!(
+                    "Iteration {}: {} tool call(s), {} tokens, {} (run total: {} tokens, {})",
+                    i,
+                    output.tool_calls(),
+                    usage.total_tokens(),
+                    format_cost(usage.cost_usd),
+                    budget.spent().total_tokens(),
+                    format_cost(budget.spent().cost_usd)
+                );
+                if usage.cost_usd.is_none() && run.max_cost.is_some() && !warned_unpriced {
+                    warned_unpriced = true;
+                    eprintln!(
+                        "Warning: no price is known for model {}; --max-cost cannot be enforced (add it under [prices] in config)",
+                        run.model.as_deref().unwrap_or("(default)")
+                    );
+                }
+            }
+            None if budget.is_limited() => {
+                eprintln!(
+                    "Warning: {} reported no token usage; the budget cannot be enforced",
+                    tool.name()
+                );
+            }
+            None => {}
+        }
+
+        let stall = stalls.record(&IterationChanges {
+            committed: checkpoint.as_ref().is_some_and(|c| c.has_new_commits()),
+            files_changed: checkpoint.as_ref().is_some_and(|c| c.has_file_changes()),
+            task_files_changed: task_files_fingerprint(ctx) != task_files_before,
+            output: &output.text(),
+        });
+
+        let items_after = prd::read_items(&ctx.prd_file);
+        let prd_after = PrdProgress::count(&items_after);
+        if let Some(after) = prd_after {
+            match prd_before {
+                Some(before) if before != after => {
+                    println!("PRD progress: {} -> {} items done", before, after)
+                }
+                _ => println!("PRD progress: {} items done", after),
+            }
+        }
+
+        let item = item.map(|item| check_item(item, &items_after));
+        let checked_items: Vec<String> = prd::newly_checked(&items_before, &items_after)
+            .into_iter()
+            .map(|item| item.text.clone())
+            .collect();
+        for text in &checked_items {
+            print_checked_off(text, run.model.as_deref());
+        }
+
+        // Checking off every PRD item counts as a claim, same as the marker
+        let prd_complete = prd_after.is_some_and(|p| p.is_complete());
+        if prd_complete && !tool.is_complete(&output) && !output.interrupted {
+            println!("All PRD items are checked; treating as a completion claim");
+        }
+        let claimed = !output.interrupted && (tool.is_complete(&output) || prd_complete);
+        let checks = if claimed {
+            verify::run_checks(&run.verify)?
+        } else {
+            Vec::new()
+        };
+        if let Some(failure) = verify::first_failure(&checks) {
+            println!(
+                "Completion rejected: `{}` failed; continuing with the failure noted",
+                failure.command
+            );
+            note = Some(failure.failure_note());
+        }
+        let completed = claimed && note.is_none();
+        let items_done = !checked_items.is_empty();
+        let iteration_failed = failed || note.is_some();
+
+        let git = match checkpoint {
+            Some(ref checkpoint) => Some(check_iteration_git(checkpoint, &run, iteration_failed)?),
+            None => None,
+        };
+        let details = IterationDetails {
+            completed,
+            timed_out: output.timed_out,
+            interrupted: output.interrupted,
+            attempts,
+            git,
+            usage,
+            prd_before,
+            prd_after,
+            item,
+            model: run.model.clone(),
+            checked_items,
+            stall: stall.clone(),
+            checks,
+        };
+        recorder.record_iteration(i, started_at, &prompt, &output, details)?;
+
+        if completed {
             println!("\nPRD complete after {} iteration(s).", i);
-            return Ok(());
+            return Ok(RunOutcome::Complete { iterations: i });
+        }
+
+        if output.interrupted || signals::check() != Interrupt::None {
+            println!(
+                "\nInterrupted after {} iteration(s); continue with `ralph resume`.",
+                i
+            );
+            return Ok(RunOutcome::Interrupted { iterations: i });
+        }
+
+        let mut next_model = if items_done {
+            escalation
+                .item_done()
+                .map(|step| (step.clone(), "PRD item checked off"))
+        } else if iteration_failed {
+            escalation
+                .failed()
+                .map(|step| (step.clone(), "repeated failed iterations"))
+        } else {
+            escalation.succeeded();
+            None
+        };
+
+        if let Some(reason) = stall {
+            println!("\nRun stalled: {}", reason);
+            // A stronger model is the first answer to a stall
+            if next_model.is_none() {
+                next_model = escalation
+                    .escalate()
+                    .map(|step| (step.clone(), "run stalled"));
+            }
+            let action = if nudged {
+                StallAction::Abort
+            } else {
+                run.stall.action
+            };
+            let resume = next_model.is_some()
+                || match action {
+                    StallAction::Abort => false,
+                    StallAction::Pause => confirm_continue()?,
+                    StallAction::Nudge => {
+                        println!("Asking the agent to change its approach");
+                        let nudge = stall::nudge_note(&reason);
+                        note = Some(match note.take() {
+                            Some(note) => format!("{}\n\n{}", note, nudge),
+                            None => nudge,
+                        });
+                        nudged = true;
+                        true
+                    }
+                };
+            if !resume {
+                println!(
+                    "\nStopped after {} iteration(s); continue with `ralph resume` once unblocked.",
+                    i
+                );
+                return Ok(RunOutcome::Stalled {
+                    iterations: i,
+                    reason,
+                });
+            }
+            stalls.reset();
+        }
+
+        if let Some((step, why)) = next_model {
+            println!("Switching to model {} ({})", step.model, why);
+            run.model = Some(step.model);
+            run.price = step.price;
+            tool = create_tool(&run, rules);
+            recorder.record_escalation(escalation.level())?;
         }
     }
 
-    println!("\nCompleted {} iteration(s).", cli.iterations);
-    Ok(())
+    println!(
+        "\nCompleted {} iteration(s) without the PRD being marked complete.",
+        run.iterations
+    );
+    Ok(RunOutcome::Exhausted {
+        iterations: run.iterations,
+    })
 }
 
-fn build_prompt(ctx: &TaskContext, tool: &ToolChoice) -> String {
-    let attachments = match tool {
-        ToolChoice::Codex => format!(
-            "PRD file: {}. PROGRESS file: {}.",
-            ctx.prd_file.display(),
-            ctx.progress_file.display()
-        ),
-        _ => format!(
-            "@{} @{} @~/.agents/AGENTS.md",
-            ctx.progress_file.display(),
-            ctx.prd_file.display()
-        ),
-    };
+/// Contents of the PRD and PROGRESS files, to tell whether an iteration touched them
+fn task_files_fingerprint(ctx: &TaskContext) -> (Option<u64>, Option<u64>) {
+    (
+        stall::file_fingerprint(&ctx.prd_file),
+        stall::file_fingerprint(&ctx.progress_file),
+    )
+}
 
-    let rules_line = match tool {
-        ToolChoice::Codex => "YOU **MUST** OBEY RULES SPECIFIED IN ~/.agents/AGENTS.md.",
-        _ => "YOU **MUST** OBEY RULES SPECIFIED IN @~/.agents/AGENTS.md.",
-    };
+/// Stalls and checked-off PRD items, repeated at the end of a run
+fn print_run_summary(recorder: &RunRecorder) {
+    for (iteration, reason) in recorder.stalls() {
+        println!("Stall after iteration {}: {}", iteration, reason);
+    }
+    for (text, model) in recorder.checked_items() {
+        print_checked_off(text, model);
+    }
+}
 
-    format!(
-        "{} \
-        1. Find the highest-priority task and implement it. \
-        2. Run your tests and type checks. \
-        3. Update the PRD with what was done. \
-        4. Append your progress to PROGRESS.md. \
-        5. Commit your changes. \
-        ONLY WORK ON A SINGLE TASK. \
-        {} \
-        If the PRD is complete, output <promise>COMPLETE</promise>.",
-        attachments, rules_line
-    )
+fn print_checked_off(text: &str, model: Option<&str>) {
+    match model {
+        Some(model) => println!("Checked off by {}: {}", model, text),
+        None => println!("Checked off: {}", text),
+    }
 }
 
-fn confirm_proceed() -> Result<bool> {
-    print!("Proceed with these files? [y/N] ");
-    io::stdout().flush().ok();
+/// Next PRD item for `--select-item`, or `None` to leave the choice to the agent
+fn select_item(items: &[PrdItem]) -> Option<PrdItem> {
+    match prd::next_item(items) {
+        Some(item) => {
+            println!("Selected PRD item (line {}): {}\n", item.line, item.text);
+            Some(item.clone())
+        }
+        None => {
+            eprintln!("Warning: no unchecked PRD items to select; leaving the choice to the agent");
+            None
+        }
+    }
+}
 
-    let mut input = String::new();
-    io::stdin()
-        .read_line(&mut input)
-        .map_err(|e| RalphError::ToolFailed {
-            tool: "stdin".into(),
-            source: e,
-        })?;
+/// Whether the iteration checked off its selected item
+fn check_item(item: PrdItem, items: &[PrdItem]) -> ItemRecord {
+    let checked = items.iter().any(|i| i.checked && item.same_item(i));
+    if !checked {
+        eprintln!(
+            "Warning: selected PRD item was not checked off: {}",
+            item.text
+        );
+    }
+    ItemRecord {
+        text: item.text,
+        line: item.line,
+        checked,
+    }
+}
 
-    Ok(input.trim().eq_ignore_ascii_case("y"))
+/// Usage reported by the tool, with the cost estimated from the price table
+/// when the tool does not report it
+fn iteration_usage(run: &RunConfig, output: &ToolOutput) -> Option<Usage> {
+    let mut usage = output.usage()?;
+    if usage.cost_usd.is_none() {
+        usage.cost_usd = run.price.map(|price| price.cost(&usage));
+    }
+    Some(usage)
+}
+
+fn format_cost(cost: Option<f64>) -> String {
+    cost.map_or_else(|| "cost unknown".to_string(), |c| format!("${:.4}", c))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
+/// Run the tool, retrying transient failures with exponential backoff.
+/// Returns the final attempt's output and the number of attempts made.
+fn run_with_retry(
+    run: &RunConfig,
+    ctx: &TaskContext,
+    tool: &dyn Tool,
+    prompt: &str,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<(ToolOutput, u32)> {
+    let mut attempt = 1;
+    loop {
+        let result = tool.run(ctx, prompt, run.iteration_timeout);
+        let retryable = match result {
+            Ok(ref output) => retry::is_retryable_output(output),
+            Err(ref e) => retry::is_retryable_error(e),
+        };
+        if !retryable || attempt >= run.retry.max_attempts || signals::check() != Interrupt::None {
+            return result.map(|output| (output, attempt));
+        }
+
+        let delay = run.retry.delay(attempt);
+        match result {
+            Ok(ref output) => eprintln!(
+                "Attempt {}/{} exited with status {:?}; retrying in {}s",
+                attempt,
+                run.retry.max_attempts,
+                output.status.code(),
+                delay.as_secs()
+            ),
+            Err(ref e) => eprintln!(
+                "Attempt {}/{} failed: {}; retrying in {}s",
+                attempt,
+                run.retry.max_attempts,
+                e,
+                delay.as_secs()
+            ),
+        }
 
-    fn mock_task_context() -> TaskContext {
-        TaskContext {
-            dir: PathBuf::from("/test/dir"),
-            prd_file: PathBuf::from("/test/dir/PRD.md"),
-            progress_file: PathBuf::from("/test/dir/PROGRESS.md"),
+        // Don't let a half-finished attempt leak into the retry
+        if let (true, Some(checkpoint)) = (run.rollback, checkpoint) {
+            checkpoint.rollback()?;
         }
+        if !signals::sleep(delay) {
+            return result.map(|output| (output, attempt));
+        }
+        attempt += 1;
     }
+}
 
-    #[test]
-    fn test_build_prompt_opencode() {
-        let ctx = mock_task_context();
-        let prompt = build_prompt(&ctx, &ToolChoice::OpenCode);
+/// Report whether the agent committed, rolling back failed iterations if configured
+fn check_iteration_git(
+    checkpoint: &Checkpoint,
+    run: &RunConfig,
+    failed: bool,
+) -> Result<GitRecord> {
+    let committed = checkpoint.has_new_commits();
+    if !committed && !failed {
+        eprintln!("Warning: iteration finished without creating a commit");
+    }
 
-        assert!(prompt.contains("@/test/dir/PROGRESS.md"));
-        assert!(prompt.contains("@/test/dir/PRD.md"));
-        assert!(prompt.contains("@~/.agents/AGENTS.md"));
-        assert!(prompt.contains("highest-priority task"));
-        assert!(prompt.contains("<promise>COMPLETE</promise>"));
+    let rolled_back = failed && run.rollback;
+    if rolled_back {
+        checkpoint.rollback()?;
+        println!(
+            "Rolled back working tree to checkpoint {}",
+            git::short(&checkpoint.head)
+        );
     }
 
-    #[test]
-    fn test_build_prompt_claude() {
-        let ctx = mock_task_context();
-        let prompt = build_prompt(&ctx, &ToolChoice::Claude);
+    Ok(GitRecord {
+        head_before: checkpoint.head.clone(),
+        head_after: git::head(Path::new(".")),
+        committed,
+        rolled_back,
+    })
+}
 
-        assert!(prompt.contains("@/test/dir/PROGRESS.md"));
-        assert!(prompt.contains("@/test/dir/PRD.md"));
-        assert!(prompt.contains("@~/.agents/AGENTS.md"));
+/// Stall action `pause`: wait for a human to decide; without a terminal, stop
+fn confirm_continue() -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        eprintln!("Cannot pause for input (stdin is not a terminal); stopping instead");
+        return Ok(false);
     }
+    confirm("Paused. Fix what is blocking the agent, then continue the run?")
+}
 
-    #[test]
-    fn test_build_prompt_codex() {
-        let ctx = mock_task_context();
-        let prompt = build_prompt(&ctx, &ToolChoice::Codex);
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush().ok();
 
-        assert!(prompt.contains("PRD file: /test/dir/PRD.md"));
-        assert!(prompt.contains("PROGRESS file: /test/dir/PROGRESS.md"));
-        assert!(prompt.contains("~/.agents/AGENTS.md"));
-        assert!(!prompt.contains("@~/.agents/AGENTS.md"));
-    }
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(RalphError::ConfirmFailed)?;
 
-    #[test]
-    fn test_completion_marker() {
-        assert_eq!(COMPLETION_MARKER, "<promise>COMPLETE</promise>");
-    }
+    Ok(input.trim().eq_ignore_ascii_case("y"))
 }
@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// SIGINT/SIGTERM deliveries since `install`
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+/// Deliveries already reported to the user
+static REPORTED: AtomicUsize = AtomicUsize::new(0);
+
+/// What the user asked for by interrupting ralph
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interrupt {
+    None,
+    /// First signal: let the current iteration finish, then stop
+    StopAfterIteration,
+    /// Second signal: stop the tool now
+    Abort,
+}
+
+extern "C" fn handle_signal(_: libc::c_int) {
+    // Only async-signal-safe work here; everything else happens in `check`
+    RECEIVED.fetch_add(1, Ordering::SeqCst);
+}
+
+/// Route SIGINT and SIGTERM to the interrupt counter instead of terminating.
+/// Tools run in their own process group, so a terminal Ctrl-C only reaches ralph.
+pub fn install() {
+    let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+/// Current interrupt state, printing a notice the first time each level is seen
+pub fn check() -> Interrupt {
+    let received = RECEIVED.load(Ordering::SeqCst);
+    let reported = REPORTED.swap(received, Ordering::SeqCst);
+    let interrupt = level(received);
+    if received > reported {
+        match interrupt {
+            Interrupt::StopAfterIteration => eprintln!(
+                "\nInterrupt received: stopping after the current iteration (press Ctrl-C again to abort now)"
+            ),
+            Interrupt::Abort if reported < 2 => {
+                eprintln!("\nSecond interrupt received: stopping the tool now")
+            }
+            _ => {}
+        }
+    }
+    interrupt
+}
+
+/// Sleep for `duration`, returning early (with `false`) if ralph is interrupted
+pub fn sleep(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if check() != Interrupt::None {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100).min(deadline - Instant::now()));
+    }
+    true
+}
+
+fn level(received: usize) -> Interrupt {
+    match received {
+        0 => Interrupt::None,
+        1 => Interrupt::StopAfterIteration,
+        _ => Interrupt::Abort,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_escalates_with_signal_count() {
+        assert_eq!(level(0), Interrupt::None);
+        assert_eq!(level(1), Interrupt::StopAfterIteration);
+        assert_eq!(level(2), Interrupt::Abort);
+        assert_eq!(level(5), Interrupt::Abort);
+    }
+}
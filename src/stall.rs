@@ -0,0 +1,243 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::str::FromStr;
+
+/// What the runner does when iterations stop making progress
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StallAction {
+    /// End the run (resumable with `ralph resume`)
+    Abort,
+    /// Ask a human whether to continue
+    Pause,
+    /// Tell the agent it is stuck and to change approach; abort if it stalls again
+    Nudge,
+}
+
+/// When a run counts as stalled and what to do about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct StallPolicy {
+    /// Consecutive idle iterations that make a stall (0 disables detection)
+    pub after: u32,
+    pub action: StallAction,
+}
+
+impl Default for StallPolicy {
+    fn default() -> Self {
+        Self {
+            after: 3,
+            action: StallAction::Abort,
+        }
+    }
+}
+
+impl FromStr for StallAction {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "abort" => Ok(StallAction::Abort),
+            "pause" => Ok(StallAction::Pause),
+            "nudge" => Ok(StallAction::Nudge),
+            _ => Err(format!(
+                "unknown stall action '{}' (expected abort, pause or nudge)",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for StallAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StallAction::Abort => write!(f, "abort"),
+            StallAction::Pause => write!(f, "pause"),
+            StallAction::Nudge => write!(f, "nudge"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StallAction {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for StallAction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// What changed during one iteration
+pub struct IterationChanges<'a> {
+    /// HEAD moved
+    pub committed: bool,
+    /// Tracked or untracked files differ from the checkpoint
+    pub files_changed: bool,
+    /// PRD or PROGRESS contents differ from before the iteration
+    pub task_files_changed: bool,
+    /// Everything the tool said
+    pub output: &'a str,
+}
+
+/// Counts consecutive iterations without progress
+#[derive(Debug, Default)]
+pub struct StallDetector {
+    after: u32,
+    /// Iterations in a row without commits or file changes
+    idle: u32,
+    /// Iterations in a row without progress whose output matched the previous one
+    repeated: u32,
+    last_output: Option<u64>,
+}
+
+impl StallDetector {
+    pub fn new(policy: &StallPolicy) -> Self {
+        Self {
+            after: policy.after,
+            ..Self::default()
+        }
+    }
+
+    /// Record an iteration; returns why the run is stalled, if it is
+    pub fn record(&mut self, changes: &IterationChanges) -> Option<String> {
+        let output = hash(changes.output);
+        let progressed = changes.committed || changes.files_changed || changes.task_files_changed;
+        self.idle = if progressed { 0 } else { self.idle + 1 };
+        // A repeated summary is fine as long as the agent keeps making progress
+        self.repeated = match self.last_output {
+            _ if progressed => 0,
+            Some(last) if last == output => self.repeated + 1,
+            _ => 1,
+        };
+        self.last_output = Some(output);
+
+        if self.after == 0 {
+            return None;
+        }
+        if self.repeated >= self.after.max(2) {
+            return Some(format!(
+                "identical tool output in {} consecutive iterations",
+                self.repeated
+            ));
+        }
+        if self.idle >= self.after {
+            return Some(format!(
+                "no commits, file changes or PRD/PROGRESS updates in {} consecutive iterations",
+                self.idle
+            ));
+        }
+        None
+    }
+
+    /// Start counting afresh, e.g. after a human or a nudge intervened
+    pub fn reset(&mut self) {
+        self.idle = 0;
+        self.repeated = 0;
+        self.last_output = None;
+    }
+}
+
+/// Fingerprint of a file's contents (`None` when it cannot be read)
+pub fn file_fingerprint(path: &Path) -> Option<u64> {
+    fs::read(path).ok().map(|content| hash(&content))
+}
+
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Added to the next prompt when the `nudge` action fires
+pub fn nudge_note(reason: &str) -> String {
+    format!(
+        "NOTE: ralph detected that the run is stalled ({}). \
+         Stop repeating the previous approach: re-read the PRD and PROGRESS, \
+         pick a smaller step or a different strategy, and make a concrete change you can commit.",
+        reason
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(progress: bool, output: &str) -> IterationChanges<'_> {
+        IterationChanges {
+            committed: progress,
+            files_changed: false,
+            task_files_changed: false,
+            output,
+        }
+    }
+
+    #[test]
+    fn test_idle_iterations_stall() {
+        let mut detector = StallDetector::new(&StallPolicy::default());
+        assert!(detector.record(&changes(false, "a")).is_none());
+        assert!(detector.record(&changes(false, "b")).is_none());
+        let reason = detector.record(&changes(false, "c")).unwrap();
+        assert!(reason.contains("3 consecutive iterations"));
+    }
+
+    #[test]
+    fn test_progress_resets_idle_count() {
+        let mut detector = StallDetector::new(&StallPolicy::default());
+        detector.record(&changes(false, "a"));
+        detector.record(&changes(false, "b"));
+        assert!(detector.record(&changes(true, "c")).is_none());
+        assert!(detector.record(&changes(false, "d")).is_none());
+    }
+
+    #[test]
+    fn test_identical_output_with_changes_does_not_stall() {
+        let mut detector = StallDetector::new(&StallPolicy::default());
+        for _ in 0..5 {
+            assert!(detector.record(&changes(true, "same")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_identical_output_without_changes_stalls() {
+        let mut detector = StallDetector::new(&StallPolicy::default());
+        detector.record(&changes(true, "same"));
+        detector.record(&changes(false, "same"));
+        detector.record(&changes(false, "same"));
+        let reason = detector.record(&changes(false, "same")).unwrap();
+        assert!(reason.contains("identical tool output in 3"));
+    }
+
+    #[test]
+    fn test_zero_disables_detection() {
+        let policy = StallPolicy {
+            after: 0,
+            ..StallPolicy::default()
+        };
+        let mut detector = StallDetector::new(&policy);
+        for _ in 0..10 {
+            assert!(detector.record(&changes(false, "same")).is_none());
+        }
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut detector = StallDetector::new(&StallPolicy::default());
+        detector.record(&changes(false, "a"));
+        detector.record(&changes(false, "a"));
+        detector.reset();
+        assert!(detector.record(&changes(false, "a")).is_none());
+    }
+
+    #[test]
+    fn test_stall_action_parse() {
+        assert_eq!("pause".parse(), Ok(StallAction::Pause));
+        assert!("retry".parse::<StallAction>().is_err());
+        assert_eq!(StallAction::Nudge.to_string(), "nudge");
+    }
+}
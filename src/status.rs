@@ -0,0 +1,175 @@
+use crate::cli::ToolChoice;
+use crate::manifest::{load_runs, RunManifest};
+use crate::outcome::RunOutcome;
+use crate::prd::{self, PrdProgress};
+use crate::task::TaskContext;
+use chrono::{DateTime, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Dates such as `2025-01-22` in PROGRESS headings
+static DATE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b\d{4}-\d{2}-\d{2}\b").unwrap());
+
+/// Where a task stands, as shown by `ralph list` and `ralph status` and
+/// checked when queueing tasks
+#[derive(Debug, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub dir: PathBuf,
+    pub prd_file: Option<PathBuf>,
+    pub progress: Option<PrdProgress>,
+    /// First unchecked PRD item, as `--select-item` would pick it
+    pub next_item: Option<String>,
+    /// Latest date in a PROGRESS.md heading
+    pub last_progress_entry: Option<NaiveDate>,
+    pub runs: usize,
+    pub last_run: Option<LastRun>,
+    /// Every PRD item is checked; for a PRD without a task list, a run
+    /// completed it
+    pub completed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LastRun {
+    pub dir: PathBuf,
+    pub tool: ToolChoice,
+    pub model: Option<String>,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the run is going on, or if it was killed
+    pub outcome: Option<RunOutcome>,
+    pub iterations: u32,
+}
+
+impl TaskStatus {
+    pub fn load(dir: &Path) -> Self {
+        let prd_file = TaskContext::find_prd_file(dir).ok();
+        let items = prd_file.as_deref().map(prd::read_items).unwrap_or_default();
+        let progress = PrdProgress::count(&items);
+        let last_progress_entry = TaskContext::find_progress_file(dir)
+            .ok()
+            .flatten()
+            .and_then(|path| last_progress_entry(&path));
+
+        let runs = load_runs(dir);
+        // Items added after a completed run reopen the task
+        let completed = match progress {
+            Some(progress) => progress.is_complete(),
+            None => runs
+                .iter()
+                .any(|(_, m)| matches!(m.outcome, Some(RunOutcome::Complete { .. }))),
+        };
+        let last_run = runs
+            .last()
+            .map(|(run_dir, manifest)| LastRun::new(run_dir, manifest));
+
+        Self {
+            name: dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| dir.display().to_string()),
+            dir: dir.to_path_buf(),
+            prd_file,
+            next_item: prd::next_item(&items).map(|item| item.text.clone()),
+            completed,
+            progress,
+            last_progress_entry,
+            runs: runs.len(),
+            last_run,
+        }
+    }
+}
+
+impl LastRun {
+    fn new(dir: &Path, manifest: &RunManifest) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            tool: manifest.tool.clone(),
+            model: manifest.model.clone(),
+            started_at: manifest.started_at,
+            outcome: manifest.outcome.clone(),
+            iterations: manifest.outcome.as_ref().map_or_else(
+                || manifest.iterations.last().map_or(0, |i| i.number),
+                RunOutcome::iterations,
+            ),
+        }
+    }
+
+    /// e.g. `exhausted after 5 iterations`
+    pub fn summary(&self) -> String {
+        format!(
+            "{} after {} iteration(s)",
+            self.outcome_label(),
+            self.iterations
+        )
+    }
+
+    pub fn outcome_label(&self) -> &'static str {
+        self.outcome
+            .as_ref()
+            .map_or("unfinished", RunOutcome::label)
+    }
+}
+
+/// Latest date found in a heading of the PROGRESS file
+fn last_progress_entry(path: &Path) -> Option<NaiveDate> {
+    let content = fs::read_to_string(path).ok()?;
+    content
+        .lines()
+        .filter(|line| line.trim_start().starts_with('#'))
+        .flat_map(|line| DATE.find_iter(line))
+        .filter_map(|m| NaiveDate::parse_from_str(m.as_str(), "%Y-%m-%d").ok())
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::RunArgs;
+    use crate::config::{Config, RunConfig};
+    use crate::manifest::RunRecorder;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_last_progress_entry_uses_latest_heading_date() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("PROGRESS.md");
+        fs::write(
+            &path,
+            "# Progress\n\n## Completed (2025-01-22)\nmentions 2030-01-01 in text\n\n### 2025-02-03: login\n",
+        )
+        .unwrap();
+        assert_eq!(
+            last_progress_entry(&path),
+            NaiveDate::from_ymd_opt(2025, 2, 3)
+        );
+
+        fs::write(&path, "# Progress\n\n").unwrap();
+        assert_eq!(last_progress_entry(&path), None);
+    }
+
+    #[test]
+    fn test_completed_task_reopens_when_items_are_added() {
+        let temp = tempdir().unwrap();
+        let ctx = TaskContext::create(&temp.path().join("login"), "- [x] one\n").unwrap();
+        assert!(TaskStatus::load(&ctx.dir).completed);
+
+        let cli = RunArgs {
+            tool: Some(ToolChoice::Claude),
+            iterations: Some(1),
+            ..RunArgs::default()
+        };
+        let run = RunConfig::resolve(&cli, &Config::default()).unwrap();
+        let mut recorder = RunRecorder::create(&ctx, &run, "prompt").unwrap();
+        recorder
+            .finish(&RunOutcome::Complete { iterations: 1 })
+            .unwrap();
+        fs::write(&ctx.prd_file, "- [x] one\n- [ ] two\n").unwrap();
+        assert!(!TaskStatus::load(&ctx.dir).completed);
+
+        fs::write(&ctx.prd_file, "# Notes only\n").unwrap();
+        assert!(TaskStatus::load(&ctx.dir).completed);
+    }
+}
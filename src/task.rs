@@ -2,6 +2,13 @@ use crate::error::{RalphError, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+/// Root directory for named tasks
+pub const TASKS_ROOT: &str = ".ai/tasks";
+
+/// Contents of a freshly created PROGRESS.md
+const PROGRESS_TEMPLATE: &str = "# Progress\n\n";
+
+#[derive(Clone)]
 pub struct TaskContext {
     pub dir: PathBuf,
     pub prd_file: PathBuf,
@@ -38,13 +45,39 @@ impl TaskContext {
         })
     }
 
-    fn resolve_directory(input: &str) -> Result<PathBuf> {
+    /// Create a new task directory holding `prd` as PRD.md and an empty PROGRESS.md
+    pub fn create(dir: &Path, prd: &str) -> Result<Self> {
+        if dir.exists() {
+            return Err(RalphError::TaskExists {
+                path: dir.to_path_buf(),
+            });
+        }
+        let create_error = |path: &Path, source| RalphError::TaskCreate {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        fs::create_dir_all(dir).map_err(|e| create_error(dir, e))?;
+        let prd_file = dir.join("PRD.md");
+        fs::write(&prd_file, prd).map_err(|e| create_error(&prd_file, e))?;
+        let progress_file = dir.join("PROGRESS.md");
+        fs::write(&progress_file, PROGRESS_TEMPLATE)
+            .map_err(|e| create_error(&progress_file, e))?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            prd_file,
+            progress_file,
+        })
+    }
+
+    pub fn resolve_directory(input: &str) -> Result<PathBuf> {
         let path = Path::new(input);
         if path.is_dir() {
             return Ok(path.to_path_buf());
         }
 
-        let task_path = Path::new(".ai/tasks").join(input);
+        let task_path = Path::new(TASKS_ROOT).join(input);
         if task_path.is_dir() {
             return Ok(task_path);
         }
@@ -60,7 +93,7 @@ impl TaskContext {
             return Ok(path.to_path_buf());
         }
 
-        let task_prd_path = Path::new(".ai/tasks").join(input).join("PRD.md");
+        let task_prd_path = Path::new(TASKS_ROOT).join(input).join("PRD.md");
         if task_prd_path.is_file() {
             return Ok(task_prd_path);
         }
@@ -70,7 +103,20 @@ impl TaskContext {
         })
     }
 
-    fn find_prd_file(dir: &Path) -> Result<PathBuf> {
+    /// Task directories under `root`, sorted by name
+    pub fn list(root: &Path) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = fs::read_dir(root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        dirs.sort();
+        dirs
+    }
+
+    pub fn find_prd_file(dir: &Path) -> Result<PathBuf> {
         for entry in fs::read_dir(dir).map_err(|_| RalphError::TaskNotFound {
             path: dir.to_path_buf(),
         })? {
@@ -87,8 +133,8 @@ impl TaskContext {
         })
     }
 
-    fn find_or_create_progress_file(dir: &Path) -> Result<PathBuf> {
-        // First try to find existing PROGRESS file
+    /// Existing PROGRESS file of a task, without creating one
+    pub fn find_progress_file(dir: &Path) -> Result<Option<PathBuf>> {
         for entry in fs::read_dir(dir).map_err(|_| RalphError::TaskNotFound {
             path: dir.to_path_buf(),
         })? {
@@ -97,13 +143,21 @@ impl TaskContext {
             })?;
             let name = entry.file_name().to_string_lossy().to_uppercase();
             if name.contains("PROGRESS") && entry.path().is_file() {
-                return Ok(entry.path());
+                return Ok(Some(entry.path()));
             }
         }
+        Ok(None)
+    }
+
+    fn find_or_create_progress_file(dir: &Path) -> Result<PathBuf> {
+        // First try to find existing PROGRESS file
+        if let Some(path) = Self::find_progress_file(dir)? {
+            return Ok(path);
+        }
 
         // Create new PROGRESS.md if not found
         let progress_path = dir.join("PROGRESS.md");
-        fs::write(&progress_path, "# Progress\n\n").ok();
+        fs::write(&progress_path, PROGRESS_TEMPLATE).ok();
         println!("Created empty PROGRESS.md at: {}", progress_path.display());
         Ok(progress_path)
     }
@@ -169,6 +223,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_list_task_dirs() {
+        let temp = tempdir().unwrap();
+        fs::create_dir(temp.path().join("b")).unwrap();
+        fs::create_dir(temp.path().join("a")).unwrap();
+        fs::write(temp.path().join("notes.md"), "").unwrap();
+
+        assert_eq!(
+            TaskContext::list(temp.path()),
+            [temp.path().join("a"), temp.path().join("b")]
+        );
+        assert!(TaskContext::list(&temp.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn test_find_progress_file_does_not_create() {
+        let temp = tempdir().unwrap();
+        assert_eq!(TaskContext::find_progress_file(temp.path()).unwrap(), None);
+        assert!(!temp.path().join("PROGRESS.md").exists());
+    }
+
     #[test]
     fn test_find_or_create_progress_file_exists() {
         let temp = tempdir().unwrap();
@@ -207,4 +282,22 @@ mod tests {
         assert_eq!(ctx.prd_file, prd_path);
         assert_eq!(ctx.progress_file, progress_path);
     }
+
+    #[test]
+    fn test_create_writes_prd_and_progress() {
+        let temp = tempdir().unwrap();
+        let dir = temp.path().join("tasks/login");
+
+        let ctx = TaskContext::create(&dir, "# PRD: Login\n").unwrap();
+        assert_eq!(fs::read_to_string(&ctx.prd_file).unwrap(), "# PRD: Login\n");
+        assert_eq!(
+            fs::read_to_string(&ctx.progress_file).unwrap(),
+            PROGRESS_TEMPLATE
+        );
+
+        match TaskContext::create(&dir, "again") {
+            Err(RalphError::TaskExists { path }) => assert_eq!(path, dir),
+            _ => panic!("Expected TaskExists error"),
+        }
+    }
 }
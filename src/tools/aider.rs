@@ -0,0 +1,52 @@
+use crate::error::Result;
+use crate::task::TaskContext;
+use crate::tools::events::parse_text;
+use crate::tools::{run_command, Tool, ToolOutput};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+pub struct AiderTool {
+    model: Option<String>,
+    /// Rules files, added to the chat read-only
+    rules: Vec<PathBuf>,
+}
+
+impl AiderTool {
+    pub fn new(model: Option<String>, rules: Vec<PathBuf>) -> Self {
+        Self { model, rules }
+    }
+}
+
+impl Tool for AiderTool {
+    fn name(&self) -> &str {
+        "aider"
+    }
+
+    fn run(
+        &self,
+        ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
+        // Aider only edits files added to the chat, so the PRD and progress
+        // files are passed explicitly
+        let mut cmd = Command::new("aider");
+        cmd.args(["--yes-always", "--no-pretty", "--no-stream"])
+            .arg("--file")
+            .arg(&ctx.prd_file)
+            .arg("--file")
+            .arg(&ctx.progress_file)
+            .args(["--message", prompt]);
+
+        for rules in &self.rules {
+            cmd.arg("--read").arg(rules);
+        }
+
+        if let Some(ref model) = self.model {
+            cmd.args(["--model", model]);
+        }
+
+        run_command("aider", cmd, timeout, parse_text)
+    }
+}
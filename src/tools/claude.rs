@@ -1,8 +1,10 @@
-use crate::error::{RalphError, Result};
+use crate::error::Result;
 use crate::task::TaskContext;
-use crate::tools::Tool;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, ExitStatus, Stdio};
+use crate::tools::events::{input_summary, parse_json, str_field, u64_field};
+use crate::tools::{run_command, Tool, ToolEvent, ToolOutput, Usage};
+use serde_json::Value;
+use std::process::Command;
+use std::time::Duration;
 
 pub struct ClaudeTool {
     model: Option<String>,
@@ -15,45 +17,150 @@ impl ClaudeTool {
 }
 
 impl Tool for ClaudeTool {
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "claude"
     }
 
-    fn run(&self, _ctx: &TaskContext, prompt: &str) -> Result<(ExitStatus, String)> {
+    fn run(
+        &self,
+        _ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
         let mut cmd = Command::new("claude");
-        cmd.args(["--print", "--dangerously-skip-permissions", "-p", prompt]);
+        cmd.args(["--print", "--dangerously-skip-permissions"])
+            .args(["--output-format", "stream-json", "--verbose"])
+            .args(["-p", prompt]);
 
         if let Some(ref model) = self.model {
             cmd.args(["--model", model]);
         }
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::inherit());
-
-        let mut child = cmd.spawn().map_err(|e| RalphError::ToolFailed {
-            tool: "claude".into(),
-            source: e,
-        })?;
-
-        let stdout = child.stdout.take().unwrap();
-        let reader = BufReader::new(stdout);
-        let mut output = String::new();
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| RalphError::ToolFailed {
-                tool: "claude".into(),
-                source: e,
-            })?;
-            println!("{}", line);
-            output.push_str(&line);
-            output.push('\n');
+        run_command("claude", cmd, timeout, parse_line)
+    }
+}
+
+/// Parse one line of `--output-format stream-json`
+fn parse_line(line: &str) -> Vec<ToolEvent> {
+    parse_json(line, parse_message)
+}
+
+fn parse_message(value: &Value) -> Vec<ToolEvent> {
+    let content = value
+        .pointer("/message/content")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    match str_field(value, "/type") {
+        "assistant" => content
+            .iter()
+            .filter_map(|block| match str_field(block, "/type") {
+                "text" => Some(ToolEvent::Text(str_field(block, "/text").to_string())),
+                "tool_use" => Some(ToolEvent::ToolCall {
+                    name: str_field(block, "/name").to_string(),
+                    input: block.get("input").map(input_summary).unwrap_or_default(),
+                }),
+                _ => None,
+            })
+            .collect(),
+        "user" => content
+            .iter()
+            .filter(|block| str_field(block, "/type") == "tool_result")
+            .map(|block| ToolEvent::ToolResult {
+                output: result_text(block.get("content")),
+                is_error: block.get("is_error").and_then(Value::as_bool) == Some(true),
+            })
+            .collect(),
+        "result" => {
+            let usage = Usage {
+                input_tokens: u64_field(value, "/usage/input_tokens"),
+                output_tokens: u64_field(value, "/usage/output_tokens"),
+                cache_read_tokens: u64_field(value, "/usage/cache_read_input_tokens"),
+                cache_write_tokens: u64_field(value, "/usage/cache_creation_input_tokens"),
+                cost_usd: value.get("total_cost_usd").and_then(Value::as_f64),
+            };
+            vec![
+                ToolEvent::Usage(usage),
+                ToolEvent::Result {
+                    text: str_field(value, "/result").to_string(),
+                    is_error: value.get("is_error").and_then(Value::as_bool) == Some(true),
+                },
+            ]
         }
+        _ => Vec::new(),
+    }
+}
 
-        let status = child.wait().map_err(|e| RalphError::ToolFailed {
-            tool: "claude".into(),
-            source: e,
-        })?;
+/// Tool result content is either a string or a list of text blocks
+fn result_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .map(|block| str_field(block, "/text"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_assistant_message() {
+        let line = r#"{"type":"assistant","message":{"content":[{"type":"text","text":"Running tests"},{"type":"tool_use","name":"Bash","input":{"command":"cargo test"}}]}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![
+                ToolEvent::Text("Running tests".into()),
+                ToolEvent::ToolCall {
+                    name: "Bash".into(),
+                    input: "cargo test".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_tool_result() {
+        let line = r#"{"type":"user","message":{"content":[{"type":"tool_result","content":[{"type":"text","text":"ok"}],"is_error":true}]}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![ToolEvent::ToolResult {
+                output: "ok".into(),
+                is_error: true
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_result_with_usage() {
+        let line = r#"{"type":"result","subtype":"success","is_error":false,"result":"<promise>COMPLETE</promise>","total_cost_usd":0.25,"usage":{"input_tokens":100,"output_tokens":20,"cache_read_input_tokens":1000,"cache_creation_input_tokens":50}}"#;
+        let events = parse_line(line);
+        assert_eq!(
+            events[0],
+            ToolEvent::Usage(Usage {
+                input_tokens: 100,
+                output_tokens: 20,
+                cache_read_tokens: 1000,
+                cache_write_tokens: 50,
+                cost_usd: Some(0.25),
+            })
+        );
+        assert_eq!(
+            events[1],
+            ToolEvent::Result {
+                text: "<promise>COMPLETE</promise>".into(),
+                is_error: false
+            }
+        );
+    }
 
-        Ok((status, output))
+    #[test]
+    fn test_parse_ignores_system_events() {
+        assert!(parse_line(r#"{"type":"system","subtype":"init"}"#).is_empty());
     }
 }
@@ -1,8 +1,10 @@
-use crate::error::{RalphError, Result};
+use crate::error::Result;
 use crate::task::TaskContext;
-use crate::tools::Tool;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, ExitStatus, Stdio};
+use crate::tools::events::{parse_json, str_field, u64_field};
+use crate::tools::{run_command, Tool, ToolEvent, ToolOutput, Usage};
+use serde_json::Value;
+use std::process::Command;
+use std::time::Duration;
 
 pub struct CodexTool {
     model: Option<String>,
@@ -15,45 +17,137 @@ impl CodexTool {
 }
 
 impl Tool for CodexTool {
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "codex"
     }
 
-    fn run(&self, _ctx: &TaskContext, prompt: &str) -> Result<(ExitStatus, String)> {
+    fn run(
+        &self,
+        _ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
         let mut cmd = Command::new("codex");
-        cmd.args(["--full-auto", "--quiet", prompt]);
+        cmd.args(["exec", "--full-auto", "--json", prompt]);
 
         if let Some(ref model) = self.model {
             cmd.args(["--model", model]);
         }
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::inherit());
-
-        let mut child = cmd.spawn().map_err(|e| RalphError::ToolFailed {
-            tool: "codex".into(),
-            source: e,
-        })?;
-
-        let stdout = child.stdout.take().unwrap();
-        let reader = BufReader::new(stdout);
-        let mut output = String::new();
-
-        for line in reader.lines() {
-            let line = line.map_err(|e| RalphError::ToolFailed {
-                tool: "codex".into(),
-                source: e,
-            })?;
-            println!("{}", line);
-            output.push_str(&line);
-            output.push('\n');
+        run_command("codex", cmd, timeout, parse_line)
+    }
+}
+
+/// Parse one line of `codex exec --json`
+fn parse_line(line: &str) -> Vec<ToolEvent> {
+    parse_json(line, parse_event)
+}
+
+fn parse_event(value: &Value) -> Vec<ToolEvent> {
+    let item = value.get("item").unwrap_or(&Value::Null);
+    let event = match (str_field(value, "/type"), str_field(item, "/type")) {
+        ("item.started", "command_execution") => ToolEvent::ToolCall {
+            name: "shell".to_string(),
+            input: str_field(item, "/command").to_string(),
+        },
+        ("item.completed", "command_execution") => ToolEvent::ToolResult {
+            output: str_field(item, "/aggregated_output").to_string(),
+            is_error: item.get("exit_code").and_then(Value::as_i64) != Some(0),
+        },
+        ("item.completed", "agent_message") => {
+            ToolEvent::Text(str_field(item, "/text").to_string())
+        }
+        ("item.completed", "file_change") => ToolEvent::ToolCall {
+            name: "apply_patch".to_string(),
+            input: item
+                .get("changes")
+                .and_then(Value::as_array)
+                .map(|changes| {
+                    let paths: Vec<&str> = changes.iter().map(|c| str_field(c, "/path")).collect();
+                    paths.join(", ")
+                })
+                .unwrap_or_default(),
+        },
+        ("item.started", "mcp_tool_call") => ToolEvent::ToolCall {
+            name: format!(
+                "{}.{}",
+                str_field(item, "/server"),
+                str_field(item, "/tool")
+            ),
+            input: item
+                .get("arguments")
+                .map(Value::to_string)
+                .unwrap_or_default(),
+        },
+        ("item.completed", "error") => ToolEvent::Error(str_field(item, "/message").to_string()),
+        ("turn.completed", _) => {
+            // OpenAI reports cached tokens as a subset of the input tokens
+            let cached = u64_field(value, "/usage/cached_input_tokens");
+            ToolEvent::Usage(Usage {
+                input_tokens: u64_field(value, "/usage/input_tokens").saturating_sub(cached),
+                output_tokens: u64_field(value, "/usage/output_tokens"),
+                cache_read_tokens: cached,
+                ..Default::default()
+            })
         }
+        ("turn.failed", _) => ToolEvent::Error(str_field(value, "/error/message").to_string()),
+        ("error", _) => ToolEvent::Error(str_field(value, "/message").to_string()),
+        _ => return Vec::new(),
+    };
+    vec![event]
+}
 
-        let status = child.wait().map_err(|e| RalphError::ToolFailed {
-            tool: "codex".into(),
-            source: e,
-        })?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_agent_message() {
+        let line = r#"{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"Done"}}"#;
+        assert_eq!(parse_line(line), vec![ToolEvent::Text("Done".into())]);
+    }
+
+    #[test]
+    fn test_parse_command_execution() {
+        let started = r#"{"type":"item.started","item":{"type":"command_execution","command":"bash -lc ls","status":"in_progress"}}"#;
+        assert_eq!(
+            parse_line(started),
+            vec![ToolEvent::ToolCall {
+                name: "shell".into(),
+                input: "bash -lc ls".into()
+            }]
+        );
+
+        let completed = r#"{"type":"item.completed","item":{"type":"command_execution","command":"bash -lc ls","aggregated_output":"src\n","exit_code":0}}"#;
+        assert_eq!(
+            parse_line(completed),
+            vec![ToolEvent::ToolResult {
+                output: "src\n".into(),
+                is_error: false
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_turn_usage() {
+        let line = r#"{"type":"turn.completed","usage":{"input_tokens":1200,"cached_input_tokens":1000,"output_tokens":30}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![ToolEvent::Usage(Usage {
+                input_tokens: 200,
+                output_tokens: 30,
+                cache_read_tokens: 1000,
+                ..Default::default()
+            })]
+        );
+    }
 
-        Ok((status, output))
+    #[test]
+    fn test_parse_turn_failed() {
+        let line = r#"{"type":"turn.failed","error":{"message":"rate limited"}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![ToolEvent::Error("rate limited".into())]
+        );
     }
 }
@@ -0,0 +1,244 @@
+use crate::error::Result;
+use crate::task::TaskContext;
+use crate::tools::events::parse_text;
+use crate::tools::{run_command, Tool, ToolOutput, COMPLETION_MARKER};
+use regex::Regex;
+use std::process::Command;
+use std::time::Duration;
+
+/// A backend defined entirely in config (`[tools.<name>]` with a `command`)
+#[derive(Debug, Clone)]
+pub struct CommandSpec {
+    /// argv template; `{prompt}`, `{model}`, `{variant}` and `{task_dir}` are substituted
+    pub argv: Vec<String>,
+    /// Literal completion marker to look for instead of the default
+    pub completion: Option<String>,
+    /// Regex matched against stdout to detect completion (takes precedence)
+    pub completion_regex: Option<Regex>,
+}
+
+pub struct CommandTool {
+    name: String,
+    spec: CommandSpec,
+    model: Option<String>,
+    variant: Option<String>,
+}
+
+impl CommandTool {
+    pub fn new(
+        name: String,
+        spec: CommandSpec,
+        model: Option<String>,
+        variant: Option<String>,
+    ) -> Self {
+        Self {
+            name,
+            spec,
+            model,
+            variant,
+        }
+    }
+
+    /// Expand the argv template. Arguments referring to an unset `{model}` or
+    /// `{variant}` are dropped, so flags should be written as `--model={model}`.
+    fn argv(&self, ctx: &TaskContext, prompt: &str) -> Vec<String> {
+        let task_dir = ctx.dir.display().to_string();
+        let values = [
+            ("{prompt}", Some(prompt)),
+            ("{model}", self.model.as_deref()),
+            ("{variant}", self.variant.as_deref()),
+            ("{task_dir}", Some(task_dir.as_str())),
+        ];
+
+        self.spec
+            .argv
+            .iter()
+            .filter_map(|arg| expand(arg, &values))
+            .collect()
+    }
+
+    fn matches_completion(&self, text: &str) -> bool {
+        match (&self.spec.completion_regex, &self.spec.completion) {
+            (Some(regex), _) => regex.is_match(text),
+            (None, Some(marker)) => text.contains(marker.as_str()),
+            (None, None) => text.contains(COMPLETION_MARKER),
+        }
+    }
+}
+
+impl CommandSpec {
+    /// What the agent should print to claim completion, as the prompt tells it
+    pub fn completion_marker(&self) -> String {
+        match (&self.completion_regex, &self.completion) {
+            (Some(regex), Some(marker)) if regex.is_match(marker) => marker.clone(),
+            (Some(regex), _) => format!("a line matching the regex `{}`", regex.as_str()),
+            (None, Some(marker)) => marker.clone(),
+            (None, None) => COMPLETION_MARKER.to_string(),
+        }
+    }
+}
+
+/// Placeholders substituted in a command tool's argv
+pub const PLACEHOLDERS: [&str; 4] = ["{prompt}", "{model}", "{variant}", "{task_dir}"];
+
+/// Substitute placeholders in one template argument, scanning only the
+/// template so inserted values (such as a prompt mentioning `{model}`) are
+/// kept verbatim. `None` when a placeholder's value is unset.
+fn expand(arg: &str, values: &[(&str, Option<&str>)]) -> Option<String> {
+    let mut expanded = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        match values.iter().find(|(key, _)| rest.starts_with(key)) {
+            Some((key, value)) => {
+                expanded.push_str((*value)?);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Some(expanded)
+}
+
+impl Tool for CommandTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn run(
+        &self,
+        ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
+        let argv = self.argv(ctx, prompt);
+        let mut cmd = Command::new(&argv[0]);
+        cmd.args(&argv[1..]);
+
+        run_command(&self.name, cmd, timeout, parse_text)
+    }
+
+    fn is_complete(&self, output: &ToolOutput) -> bool {
+        self.matches_completion(&output.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn spec(argv: &[&str]) -> CommandSpec {
+        CommandSpec {
+            argv: argv.iter().map(|s| s.to_string()).collect(),
+            completion: None,
+            completion_regex: None,
+        }
+    }
+
+    fn ctx() -> TaskContext {
+        TaskContext {
+            dir: PathBuf::from("/test/dir"),
+            prd_file: PathBuf::from("/test/dir/PRD.md"),
+            progress_file: PathBuf::from("/test/dir/PROGRESS.md"),
+        }
+    }
+
+    #[test]
+    fn test_argv_substitutes_placeholders() {
+        let tool = CommandTool::new(
+            "aider".into(),
+            spec(&[
+                "aider",
+                "--message",
+                "{prompt}",
+                "--model={model}",
+                "{task_dir}/PRD.md",
+            ]),
+            Some("sonnet".into()),
+            None,
+        );
+
+        assert_eq!(
+            tool.argv(&ctx(), "do it"),
+            vec![
+                "aider",
+                "--message",
+                "do it",
+                "--model=sonnet",
+                "/test/dir/PRD.md"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_argv_drops_unset_values() {
+        let tool = CommandTool::new(
+            "wrap".into(),
+            spec(&["wrap", "--model={model}", "--variant={variant}", "{prompt}"]),
+            None,
+            None,
+        );
+
+        assert_eq!(tool.argv(&ctx(), "p"), vec!["wrap", "p"]);
+    }
+
+    #[test]
+    fn test_argv_keeps_placeholders_inside_values() {
+        let tool = CommandTool::new(
+            "wrap".into(),
+            spec(&["wrap", "--dir={task_dir}", "{prompt}"]),
+            None,
+            None,
+        );
+        let prompt = "Use {model} and {variant} under {task_dir} {x}";
+
+        assert_eq!(
+            tool.argv(&ctx(), prompt),
+            vec!["wrap", "--dir=/test/dir", prompt]
+        );
+    }
+
+    #[test]
+    fn test_is_complete_default_marker() {
+        let tool = CommandTool::new("wrap".into(), spec(&["wrap"]), None, None);
+        assert!(tool.matches_completion("done <promise>COMPLETE</promise>"));
+        assert!(!tool.matches_completion("DONE"));
+    }
+
+    #[test]
+    fn test_is_complete_custom_marker_and_regex() {
+        let mut custom = spec(&["wrap"]);
+        custom.completion = Some("ALL DONE".into());
+        let tool = CommandTool::new("wrap".into(), custom.clone(), None, None);
+        assert!(tool.matches_completion("ALL DONE"));
+        assert!(!tool.matches_completion("<promise>COMPLETE</promise>"));
+
+        custom.completion_regex = Some(Regex::new(r"status:\s*complete").unwrap());
+        let tool = CommandTool::new("wrap".into(), custom, None, None);
+        assert!(tool.matches_completion("status:   complete"));
+        assert!(!tool.matches_completion("ALL DONE"));
+    }
+
+    #[test]
+    fn test_completion_marker_for_prompt() {
+        let mut custom = spec(&["wrap"]);
+        assert_eq!(custom.completion_marker(), COMPLETION_MARKER);
+
+        custom.completion = Some("ALL DONE".into());
+        assert_eq!(custom.completion_marker(), "ALL DONE");
+
+        custom.completion_regex = Some(Regex::new(r"^STATUS: COMPLETE$").unwrap());
+        assert_eq!(
+            custom.completion_marker(),
+            "a line matching the regex `^STATUS: COMPLETE$`"
+        );
+        custom.completion = Some("STATUS: COMPLETE".into());
+        assert_eq!(custom.completion_marker(), "STATUS: COMPLETE");
+    }
+}
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Longest tool call input echoed to the terminal
+const MAX_ECHO_INPUT: usize = 120;
+
+/// One thing a tool reported while running, normalized across backends
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolEvent {
+    /// Text written by the assistant
+    Text(String),
+    /// The assistant invoked a tool (shell command, file edit...)
+    ToolCall { name: String, input: String },
+    /// Output of a tool invocation
+    ToolResult { output: String, is_error: bool },
+    /// Token usage reported by the backend
+    Usage(Usage),
+    /// Final answer of the session
+    Result { text: String, is_error: bool },
+    /// Error reported by the backend
+    Error(String),
+}
+
+/// Token counts (and cost, when the backend reports it) for part of a session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+    #[serde(default)]
+    pub cache_write_tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl Usage {
+    /// All tokens processed, cached or not
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_write_tokens
+    }
+
+    pub fn add(&mut self, other: &Usage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_write_tokens += other.cache_write_tokens;
+        self.cost_usd = match (self.cost_usd, other.cost_usd) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+        };
+    }
+}
+
+impl ToolEvent {
+    /// How the event is echoed to the terminal, if at all
+    pub fn render(&self) -> Option<String> {
+        match self {
+            ToolEvent::Text(text) => Some(text.clone()),
+            ToolEvent::ToolCall { name, input } => {
+                let input = input.lines().next().unwrap_or_default();
+                let input: String = input.chars().take(MAX_ECHO_INPUT).collect();
+                Some(format!("[{}] {}", name, input))
+            }
+            ToolEvent::Result {
+                text,
+                is_error: true,
+            } => Some(format!("Error: {}", text)),
+            ToolEvent::Error(message) => Some(format!("Error: {}", message)),
+            ToolEvent::ToolResult { .. } | ToolEvent::Usage(_) | ToolEvent::Result { .. } => None,
+        }
+    }
+}
+
+/// Parses one line of a tool's stdout into events
+pub type LineParser = fn(&str) -> Vec<ToolEvent>;
+
+/// Parser for tools without a structured output mode: every line is text
+pub fn parse_text(line: &str) -> Vec<ToolEvent> {
+    vec![ToolEvent::Text(line.to_string())]
+}
+
+/// Parse a JSON object line; anything else (banners, warnings) is kept as text
+pub fn parse_json(line: &str, parse: fn(&Value) -> Vec<ToolEvent>) -> Vec<ToolEvent> {
+    match serde_json::from_str::<Value>(line) {
+        Ok(value) if value.is_object() => parse(&value),
+        _ if line.trim().is_empty() => Vec::new(),
+        _ => parse_text(line),
+    }
+}
+
+/// String field of a JSON value, or `""`
+pub fn str_field<'a>(value: &'a Value, pointer: &str) -> &'a str {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+/// Integer field of a JSON value, or 0
+pub fn u64_field(value: &Value, pointer: &str) -> u64 {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_u64)
+        .unwrap_or_default()
+}
+
+/// Compact representation of a tool call's input
+pub fn input_summary(input: &Value) -> String {
+    match input {
+        Value::String(s) => s.clone(),
+        Value::Object(map) => ["command", "file_path", "path", "pattern", "url"]
+            .iter()
+            .find_map(|key| map.get(*key).and_then(Value::as_str))
+            .map(str::to_string)
+            .unwrap_or_else(|| input.to_string()),
+        _ => input.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_json_falls_back_to_text() {
+        let parse = |_: &Value| vec![ToolEvent::Error("json".into())];
+        assert_eq!(
+            parse_json("warning: not json", parse),
+            vec![ToolEvent::Text("warning: not json".into())]
+        );
+        assert_eq!(parse_json("", parse), Vec::new());
+        assert_eq!(
+            parse_json("{}", parse),
+            vec![ToolEvent::Error("json".into())]
+        );
+    }
+
+    #[test]
+    fn test_input_summary_prefers_known_keys() {
+        assert_eq!(input_summary(&json!({"command": "ls -la"})), "ls -la");
+        assert_eq!(input_summary(&json!({"other": 1})), r#"{"other":1}"#);
+    }
+
+    #[test]
+    fn test_usage_add() {
+        let mut total = Usage::default();
+        total.add(&Usage {
+            input_tokens: 10,
+            output_tokens: 5,
+            ..Default::default()
+        });
+        assert_eq!(total.cost_usd, None);
+        total.add(&Usage {
+            input_tokens: 1,
+            cost_usd: Some(0.5),
+            ..Default::default()
+        });
+        assert_eq!(total.input_tokens, 11);
+        assert_eq!(total.output_tokens, 5);
+        assert_eq!(total.cost_usd, Some(0.5));
+    }
+
+    #[test]
+    fn test_render() {
+        let call = ToolEvent::ToolCall {
+            name: "Bash".into(),
+            input: "cargo test\nsecond line".into(),
+        };
+        assert_eq!(call.render().as_deref(), Some("[Bash] cargo test"));
+        assert_eq!(ToolEvent::Usage(Usage::default()).render(), None);
+    }
+}
@@ -0,0 +1,38 @@
+use crate::error::Result;
+use crate::task::TaskContext;
+use crate::tools::events::parse_text;
+use crate::tools::{run_command, Tool, ToolOutput};
+use std::process::Command;
+use std::time::Duration;
+
+pub struct GeminiTool {
+    model: Option<String>,
+}
+
+impl GeminiTool {
+    pub fn new(model: Option<String>) -> Self {
+        Self { model }
+    }
+}
+
+impl Tool for GeminiTool {
+    fn name(&self) -> &str {
+        "gemini"
+    }
+
+    fn run(
+        &self,
+        _ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
+        let mut cmd = Command::new("gemini");
+        cmd.args(["--yolo", "--prompt", prompt]);
+
+        if let Some(ref model) = self.model {
+            cmd.args(["--model", model]);
+        }
+
+        run_command("gemini", cmd, timeout, parse_text)
+    }
+}
@@ -1,31 +1,121 @@
 use crate::cli::ToolChoice;
+use crate::config::RunConfig;
 use crate::error::Result;
+use crate::rules::RulesFile;
 use crate::task::TaskContext;
 use std::process::ExitStatus;
+use std::time::Duration;
+
+/// Marker the agent prints once the PRD is complete
+pub const COMPLETION_MARKER: &str = "<promise>COMPLETE</promise>";
 
 /// Trait for AI tool runners (Open/Closed Principle)
 pub trait Tool {
-    fn name(&self) -> &'static str;
-    fn run(&self, ctx: &TaskContext, prompt: &str) -> Result<(ExitStatus, String)>;
+    fn name(&self) -> &str;
+    /// Run one iteration, killing the tool if it exceeds `timeout`
+    fn run(&self, ctx: &TaskContext, prompt: &str, timeout: Option<Duration>)
+        -> Result<ToolOutput>;
+
+    /// Whether the tool's output claims the PRD is complete
+    fn is_complete(&self, output: &ToolOutput) -> bool {
+        output.text().contains(COMPLETION_MARKER)
+    }
+}
+
+/// Everything a single tool invocation produced
+pub struct ToolOutput {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+    /// The tool was killed for exceeding its timeout
+    pub timed_out: bool,
+    /// The tool was stopped because the user interrupted ralph twice
+    pub interrupted: bool,
+    /// stdout parsed into events by the backend's parser
+    pub events: Vec<ToolEvent>,
+}
+
+impl ToolOutput {
+    /// Everything the assistant wrote, including its final answer
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for event in &self.events {
+            if let ToolEvent::Text(t) | ToolEvent::Result { text: t, .. } = event {
+                text.push_str(t);
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn tool_calls(&self) -> usize {
+        self.events
+            .iter()
+            .filter(|e| matches!(e, ToolEvent::ToolCall { .. }))
+            .count()
+    }
+
+    /// Token usage summed over the session, if the backend reported any
+    pub fn usage(&self) -> Option<Usage> {
+        let mut reports = self.events.iter().filter_map(|event| match event {
+            ToolEvent::Usage(usage) => Some(usage),
+            _ => None,
+        });
+        let mut total = *reports.next()?;
+        reports.for_each(|usage| total.add(usage));
+        Some(total)
+    }
+
+    /// Last error the backend reported, if any
+    pub fn last_error(&self) -> Option<&str> {
+        self.events.iter().rev().find_map(|event| match event {
+            ToolEvent::Error(message) => Some(message.as_str()),
+            ToolEvent::Result {
+                text,
+                is_error: true,
+            } => Some(text.as_str()),
+            _ => None,
+        })
+    }
 }
 
+mod aider;
 mod claude;
 mod codex;
+mod command;
+mod events;
+mod gemini;
 mod opencode;
+mod process;
 
+pub use aider::AiderTool;
 pub use claude::ClaudeTool;
 pub use codex::CodexTool;
+pub use command::{CommandSpec, CommandTool, PLACEHOLDERS};
+pub use events::{ToolEvent, Usage};
+pub use gemini::GeminiTool;
 pub use opencode::OpenCodeTool;
+use process::run_command;
 
 /// Factory function for tool selection
-pub fn create_tool(
-    choice: &ToolChoice,
-    model: Option<String>,
-    variant: Option<String>,
-) -> Box<dyn Tool> {
-    match choice {
+pub fn create_tool(run: &RunConfig, rules: &[RulesFile]) -> Box<dyn Tool> {
+    let model = run.model.clone();
+    let variant = run.variant.clone();
+    match run.tool {
         ToolChoice::OpenCode => Box::new(OpenCodeTool::new(model, variant)),
         ToolChoice::Claude => Box::new(ClaudeTool::new(model)),
         ToolChoice::Codex => Box::new(CodexTool::new(model)),
+        ToolChoice::Gemini => Box::new(GeminiTool::new(model)),
+        ToolChoice::Aider => {
+            let rules = rules.iter().map(|r| r.path.clone()).collect();
+            Box::new(AiderTool::new(model, rules))
+        }
+        ToolChoice::Command(ref name) => {
+            let spec = run
+                .command
+                .clone()
+                .expect("command tools are resolved with a CommandSpec");
+            Box::new(CommandTool::new(name.clone(), spec, model, variant))
+        }
     }
 }
@@ -1,8 +1,10 @@
-use crate::error::{RalphError, Result};
+use crate::error::Result;
 use crate::task::TaskContext;
-use crate::tools::Tool;
-use std::io::{BufRead, BufReader};
-use std::process::{Command, ExitStatus, Stdio};
+use crate::tools::events::{input_summary, parse_json, str_field, u64_field};
+use crate::tools::{run_command, Tool, ToolEvent, ToolOutput, Usage};
+use serde_json::Value;
+use std::process::Command;
+use std::time::Duration;
 
 pub struct OpenCodeTool {
     model: Option<String>,
@@ -16,13 +18,18 @@ impl OpenCodeTool {
 }
 
 impl Tool for OpenCodeTool {
-    fn name(&self) -> &'static str {
+    fn name(&self) -> &str {
         "opencode"
     }
 
-    fn run(&self, _ctx: &TaskContext, prompt: &str) -> Result<(ExitStatus, String)> {
+    fn run(
+        &self,
+        _ctx: &TaskContext,
+        prompt: &str,
+        timeout: Option<Duration>,
+    ) -> Result<ToolOutput> {
         let mut cmd = Command::new("opencode");
-        cmd.arg("run").arg(prompt);
+        cmd.arg("run").args(["--format", "json"]).arg(prompt);
 
         if let Some(ref model) = self.model {
             cmd.args(["--model", model]);
@@ -32,33 +39,108 @@ impl Tool for OpenCodeTool {
             cmd.args(["--variant", variant]);
         }
 
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::inherit());
-
-        let mut child = cmd.spawn().map_err(|e| RalphError::ToolFailed {
-            tool: "opencode".into(),
-            source: e,
-        })?;
+        run_command("opencode", cmd, timeout, parse_line)
+    }
+}
 
-        let stdout = child.stdout.take().unwrap();
-        let reader = BufReader::new(stdout);
-        let mut output = String::new();
+/// Parse one line of `opencode run --format json`
+fn parse_line(line: &str) -> Vec<ToolEvent> {
+    parse_json(line, parse_event)
+}
 
-        for line in reader.lines() {
-            let line = line.map_err(|e| RalphError::ToolFailed {
-                tool: "opencode".into(),
-                source: e,
-            })?;
-            println!("{}", line);
-            output.push_str(&line);
-            output.push('\n');
+fn parse_event(value: &Value) -> Vec<ToolEvent> {
+    let part = value.get("part").unwrap_or(&Value::Null);
+    match str_field(value, "/type") {
+        "text" => vec![ToolEvent::Text(str_field(part, "/text").to_string())],
+        "tool_use" => {
+            let status = str_field(part, "/state/status");
+            let output = match status {
+                "error" => str_field(part, "/state/error"),
+                _ => str_field(part, "/state/output"),
+            };
+            vec![
+                ToolEvent::ToolCall {
+                    name: str_field(part, "/tool").to_string(),
+                    input: part
+                        .pointer("/state/input")
+                        .map(input_summary)
+                        .unwrap_or_default(),
+                },
+                ToolEvent::ToolResult {
+                    output: output.to_string(),
+                    is_error: status == "error",
+                },
+            ]
         }
+        "step_finish" => vec![ToolEvent::Usage(Usage {
+            input_tokens: u64_field(part, "/tokens/input"),
+            output_tokens: u64_field(part, "/tokens/output") + u64_field(part, "/tokens/reasoning"),
+            cache_read_tokens: u64_field(part, "/tokens/cache/read"),
+            cache_write_tokens: u64_field(part, "/tokens/cache/write"),
+            cost_usd: part.get("cost").and_then(Value::as_f64),
+        })],
+        "error" => {
+            let message = ["/error/data/message", "/error/message", "/error/name"]
+                .iter()
+                .map(|pointer| str_field(value, pointer))
+                .find(|message| !message.is_empty())
+                .unwrap_or("unknown error");
+            vec![ToolEvent::Error(message.to_string())]
+        }
+        _ => Vec::new(),
+    }
+}
 
-        let status = child.wait().map_err(|e| RalphError::ToolFailed {
-            tool: "opencode".into(),
-            source: e,
-        })?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text() {
+        let line = r#"{"type":"text","sessionID":"s","part":{"type":"text","text":"Hello"}}"#;
+        assert_eq!(parse_line(line), vec![ToolEvent::Text("Hello".into())]);
+    }
+
+    #[test]
+    fn test_parse_tool_use() {
+        let line = r#"{"type":"tool_use","part":{"type":"tool","tool":"bash","state":{"status":"completed","input":{"command":"cargo test"},"output":"ok"}}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![
+                ToolEvent::ToolCall {
+                    name: "bash".into(),
+                    input: "cargo test".into()
+                },
+                ToolEvent::ToolResult {
+                    output: "ok".into(),
+                    is_error: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_step_finish_usage() {
+        let line = r#"{"type":"step_finish","part":{"type":"step-finish","cost":0.01,"tokens":{"input":10,"output":5,"reasoning":2,"cache":{"read":100,"write":3}}}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![ToolEvent::Usage(Usage {
+                input_tokens: 10,
+                output_tokens: 7,
+                cache_read_tokens: 100,
+                cache_write_tokens: 3,
+                cost_usd: Some(0.01),
+            })]
+        );
+    }
 
-        Ok((status, output))
+    #[test]
+    fn test_parse_error() {
+        let line =
+            r#"{"type":"error","error":{"name":"APIError","data":{"message":"overloaded"}}}"#;
+        assert_eq!(
+            parse_line(line),
+            vec![ToolEvent::Error("overloaded".into())]
+        );
     }
 }
@@ -0,0 +1,185 @@
+use crate::error::{RalphError, Result};
+use crate::signals::{self, Interrupt};
+use crate::tools::events::LineParser;
+use crate::tools::{ToolEvent, ToolOutput};
+use std::io::{self, BufRead, BufReader, Read};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often a running tool is polled for exit and timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time allowed between the first signal and SIGKILL when stopping a tool
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+/// Spawn a tool command in its own process group, capturing stdout/stderr.
+/// stdout is parsed into events with `parse` and echoed in rendered form,
+/// stderr is echoed as is. When `timeout` elapses or the user interrupts
+/// ralph a second time, the whole group is stopped.
+pub fn run_command(
+    name: &str,
+    mut cmd: Command,
+    timeout: Option<Duration>,
+    parse: LineParser,
+) -> Result<ToolOutput> {
+    let tool_failed = |e| RalphError::ToolFailed {
+        tool: name.into(),
+        source: e,
+    };
+
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    cmd.process_group(0);
+
+    let mut child = cmd.spawn().map_err(tool_failed)?;
+    let stdout_reader = echo_lines(child.stdout.take().unwrap(), move |line| {
+        let events = parse(line);
+        for rendered in events.iter().filter_map(ToolEvent::render) {
+            println!("{}", rendered);
+        }
+        events
+    });
+    let stderr_reader = echo_lines(child.stderr.take().unwrap(), |line| {
+        eprintln!("{}", line);
+        Vec::new()
+    });
+
+    // A timeout too large to represent never fires
+    let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+    let mut timed_out = false;
+    let mut interrupted = false;
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(tool_failed)? {
+            break status;
+        }
+        if signals::check() == Interrupt::Abort {
+            interrupted = true;
+            // Forward the interrupt so the tool can shut down cleanly
+            break stop_group(&mut child, libc::SIGINT).map_err(tool_failed)?;
+        }
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            timed_out = true;
+            break stop_group(&mut child, libc::SIGTERM).map_err(tool_failed)?;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let (stdout, events) = stdout_reader.join().unwrap_or_default();
+    let (stderr, _) = stderr_reader.join().unwrap_or_default();
+    Ok(ToolOutput {
+        status,
+        stdout,
+        stderr,
+        timed_out,
+        interrupted,
+        events,
+    })
+}
+
+/// Read `source` line by line on a background thread, collecting the raw
+/// text and the events `echo` returns for each line
+fn echo_lines<R, F>(source: R, echo: F) -> JoinHandle<(String, Vec<ToolEvent>)>
+where
+    R: Read + Send + 'static,
+    F: Fn(&str) -> Vec<ToolEvent> + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut output = String::new();
+        let mut events = Vec::new();
+        for line in BufReader::new(source).lines().map_while(|l| l.ok()) {
+            events.extend(echo(&line));
+            output.push_str(&line);
+            output.push('\n');
+        }
+        (output, events)
+    })
+}
+
+/// Send `signal` to every process in the child's group
+pub fn signal_group(child: &Child, signal: libc::c_int) {
+    // The child leads its own group (process_group(0)), so its pid is the pgid
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), signal);
+    }
+}
+
+/// Signal the child's process group, escalating to SIGKILL after a grace period
+fn stop_group(child: &mut Child, signal: libc::c_int) -> io::Result<std::process::ExitStatus> {
+    signal_group(child, signal);
+    let deadline = Instant::now() + KILL_GRACE;
+    while Instant::now() < deadline {
+        if let Some(status) = child.try_wait()? {
+            // Take down any descendants that ignored the first signal
+            signal_group(child, libc::SIGKILL);
+            return Ok(status);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    signal_group(child, libc::SIGKILL);
+    child.wait()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::events::parse_text;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script]);
+        cmd
+    }
+
+    #[test]
+    fn test_run_command_captures_output() {
+        let output =
+            run_command("sh", sh("echo out; echo err >&2; exit 2"), None, parse_text).unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.status.code(), Some(2));
+        assert!(!output.timed_out);
+        assert_eq!(output.events, vec![ToolEvent::Text("out".into())]);
+    }
+
+    #[test]
+    fn test_run_command_applies_parser() {
+        fn parse(line: &str) -> Vec<ToolEvent> {
+            vec![ToolEvent::Error(line.to_uppercase())]
+        }
+        let output = run_command("sh", sh("echo oops"), None, parse).unwrap();
+        assert_eq!(output.stdout, "oops\n");
+        assert_eq!(output.last_error(), Some("OOPS"));
+    }
+
+    #[test]
+    fn test_run_command_timeout_kills_descendants() {
+        let started = Instant::now();
+        // The background sleep keeps stdout open; it must be killed with the group
+        let output = run_command(
+            "sh",
+            sh("sleep 30 & echo started; wait"),
+            Some(Duration::from_millis(300)),
+            parse_text,
+        )
+        .unwrap();
+
+        assert!(output.timed_out);
+        assert!(!output.status.success());
+        assert_eq!(output.stdout, "started\n");
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_run_command_missing_binary() {
+        let result = run_command(
+            "nope",
+            Command::new("ralph-test-missing-binary"),
+            None,
+            parse_text,
+        );
+        assert!(matches!(result, Err(RalphError::ToolFailed { .. })));
+    }
+}
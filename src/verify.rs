@@ -0,0 +1,116 @@
+use crate::error::{RalphError, Result};
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::time::Instant;
+
+/// Lines of a failing check's output carried into the next prompt
+const FAILURE_TAIL_LINES: usize = 40;
+
+/// Result of one verification command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub command: String,
+    pub exit_code: Option<i32>,
+    pub passed: bool,
+    pub duration_ms: u64,
+    /// Combined stdout/stderr, only kept for failures
+    #[serde(default, skip_serializing)]
+    pub output: String,
+}
+
+impl CheckRecord {
+    /// Note for the next prompt explaining why completion was rejected
+    pub fn failure_note(&self) -> String {
+        let lines: Vec<&str> = self.output.lines().collect();
+        let tail = &lines[lines.len().saturating_sub(FAILURE_TAIL_LINES)..];
+        format!(
+            "NOTE: The previous iteration claimed the PRD was complete, but verification failed. \
+            `{}` exited with status {}. Fix the failure before claiming completion again. \
+            Last lines of output:\n{}",
+            self.command,
+            self.exit_code
+                .map_or_else(|| "unknown".to_string(), |c| c.to_string()),
+            tail.join("\n")
+        )
+    }
+}
+
+/// Run verification commands in order through `sh -c`, stopping at the first failure
+pub fn run_checks(commands: &[String]) -> Result<Vec<CheckRecord>> {
+    let mut records = Vec::new();
+
+    for command in commands {
+        println!("Verifying: {}", command);
+        let started = Instant::now();
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .output()
+            .map_err(|e| RalphError::VerifyFailed {
+                command: command.clone(),
+                source: e,
+            })?;
+
+        let passed = output.status.success();
+        let mut combined = String::from_utf8_lossy(&output.stdout).into_owned();
+        combined.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        println!("  {}", if passed { "passed" } else { "FAILED" });
+        records.push(CheckRecord {
+            command: command.clone(),
+            exit_code: output.status.code(),
+            passed,
+            duration_ms: started.elapsed().as_millis() as u64,
+            output: if passed { String::new() } else { combined },
+        });
+
+        if !passed {
+            break;
+        }
+    }
+
+    Ok(records)
+}
+
+/// First failed check, if any
+pub fn first_failure(records: &[CheckRecord]) -> Option<&CheckRecord> {
+    records.iter().find(|r| !r.passed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_checks_all_pass() {
+        let records = run_checks(&["true".into(), "echo ok".into()]).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(first_failure(&records).is_none());
+    }
+
+    #[test]
+    fn test_run_checks_stops_at_first_failure() {
+        let records = run_checks(&["echo broken; exit 3".into(), "true".into()]).unwrap();
+        assert_eq!(records.len(), 1);
+
+        let failure = first_failure(&records).unwrap();
+        assert_eq!(failure.exit_code, Some(3));
+        assert_eq!(failure.output, "broken\n");
+    }
+
+    #[test]
+    fn test_failure_note_includes_command_and_tail() {
+        let record = CheckRecord {
+            command: "cargo test".into(),
+            exit_code: Some(101),
+            passed: false,
+            duration_ms: 5,
+            output: (1..=50).map(|i| format!("line {}\n", i)).collect(),
+        };
+
+        let note = record.failure_note();
+        assert!(note.contains("`cargo test` exited with status 101"));
+        assert!(note.contains("line 50"));
+        assert!(note.contains("line 11\n"));
+        assert!(!note.contains("line 10\n"));
+    }
+}